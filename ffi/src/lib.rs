@@ -0,0 +1,6 @@
+//! Linkable build of `polygraph`'s C ABI, see `polygraph::ffi`.
+//!
+//! Builds `libpolygraph_ffi.so` (`.dylib`, `.dll`) and `libpolygraph_ffi.a` (`.lib`),
+//! exporting every function of `polygraph::ffi`.
+
+pub use polygraph::ffi::*;
@@ -0,0 +1,88 @@
+//! Detection of allocations on the audio thread, for debug builds and tests.
+//!
+//! Allocations are only seen if [`GuardedAllocator`] is the global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: GuardedAllocator<System> = GuardedAllocator(System);
+//! ```
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+};
+
+thread_local! {
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    static CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record_call() {
+    // `try_with`, as the allocator may be called while thread locals are being destroyed
+    if GUARDED.try_with(Cell::get).unwrap_or(false) {
+        let _ = CALLS.try_with(|calls| calls.set(calls.get() + 1));
+    }
+}
+
+/// Wraps a global allocator, counting calls to it made, on each thread, while in
+/// [`count_allocations`] or [`assert_no_alloc`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuardedAllocator<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for GuardedAllocator<A> {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_call();
+        self.0.alloc(layout)
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_call();
+        self.0.alloc_zeroed(layout)
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_call();
+        self.0.realloc(ptr, layout, new_size)
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_call();
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+/// Runs `f`, returns it's result, and the number of (de/re)allocations it made
+/// on the current thread.
+#[inline]
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let was_guarded = GUARDED.replace(true);
+    let calls_before = CALLS.replace(0);
+
+    let result = f();
+
+    GUARDED.set(was_guarded);
+    let calls = CALLS.replace(calls_before);
+    // nested calls count towards the outer ones
+    CALLS.set(calls_before + calls);
+
+    (result, calls)
+}
+
+/// Runs `f` and returns it's result.
+///
+/// # Panics
+///
+/// if `f` (de/re)allocated on the current thread
+#[inline]
+pub fn assert_no_alloc<R>(f: impl FnOnce() -> R) -> R {
+    let (result, calls) = count_allocations(f);
+    assert_eq!(
+        calls, 0,
+        "{calls} allocator calls made in a no-alloc section"
+    );
+    result
+}
@@ -0,0 +1,235 @@
+use super::*;
+
+/// An edge, from an output port to an input port.
+pub type Edge = ((NodeID, OutputID), (NodeID, InputID));
+
+impl AudioGraph {
+    /// Pushes every node `node` depends on, then `node` itself, to `order`,
+    /// skipping nodes in `visited`.
+    pub(crate) fn visit_post_order(
+        &self,
+        node: &NodeID,
+        visited: &mut FnvHashSet<NodeID>,
+        order: &mut Vec<NodeID>,
+    ) {
+        if !visited.insert(node.clone()) {
+            return;
+        }
+
+        for source in self[node]
+            .inputs()
+            .values()
+            .flat_map(|input| input.connections().keys())
+        {
+            self.visit_post_order(source, visited, order);
+        }
+
+        order.push(node.clone());
+    }
+
+    /// Latency accumulated along the longest path ending at (and including) `node`.
+    fn upstream_latency(&self, node: &NodeID, memo: &mut FnvHashMap<NodeID, u64>) -> u64 {
+        if let Some(&latency) = memo.get(node) {
+            return latency;
+        }
+
+        let this_node = &self[node];
+
+        let max_input_latency = this_node
+            .inputs()
+            .values()
+            .flat_map(|input| input.connections().keys())
+            .map(|source| self.upstream_latency(source, memo))
+            .max()
+            .unwrap_or(0);
+
+        let latency = max_input_latency.saturating_add(this_node.latency);
+        memo.insert(node.clone(), latency);
+        latency
+    }
+
+    /// Returns the total latency of the graph, when processing `root_nodes`, and the chain of
+    /// nodes responsible for it, in processing order.
+    ///
+    /// # Panics
+    ///
+    /// if no node exists for any of the ids in `root_nodes`
+    pub fn critical_path(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+    ) -> (u64, Vec<NodeID>) {
+        let mut memo = FnvHashMap::default();
+
+        let Some((total, root)) = root_nodes
+            .into_iter()
+            .map(|root| (self.upstream_latency(&root, &mut memo), root))
+            .max_by_key(|(latency, _)| *latency)
+        else {
+            return (0, vec![]);
+        };
+
+        let mut path = vec![root];
+
+        while let Some(source) = self[path.last().unwrap()]
+            .inputs()
+            .values()
+            .flat_map(|input| input.connections().keys())
+            .max_by_key(|source| self.upstream_latency(source, &mut memo))
+            .cloned()
+        {
+            path.push(source);
+        }
+
+        path.reverse();
+        (total, path)
+    }
+
+    /// Returns, for every node processed when processing `root_nodes`, how much latency
+    /// could be added to it without increasing the total latency of the graph.
+    ///
+    /// # Panics
+    ///
+    /// if no node exists for any of the ids in `root_nodes`
+    pub fn latency_slack(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+    ) -> FnvHashMap<NodeID, u64> {
+        let mut upstream = FnvHashMap::default();
+        let mut downstream = FnvHashMap::<NodeID, u64>::default();
+
+        let roots = Vec::from_iter(root_nodes);
+
+        for root in &roots {
+            self.upstream_latency(root, &mut upstream);
+        }
+
+        let total = roots.iter().map(|root| upstream[root]).max().unwrap_or(0);
+
+        // consumers first
+        let mut order = vec![];
+        let mut visited = FnvHashSet::default();
+        for root in &roots {
+            self.visit_post_order(root, &mut visited, &mut order);
+        }
+        order.reverse();
+
+        for node in &order {
+            let this_node = &self[node];
+            let downstream_latency = downstream.get(node).copied().unwrap_or(0);
+
+            for source in this_node
+                .inputs()
+                .values()
+                .flat_map(|input| input.connections().keys())
+            {
+                let entry = downstream.entry(source.clone()).or_default();
+                *entry = (*entry).max(downstream_latency.saturating_add(this_node.latency));
+            }
+        }
+
+        order
+            .into_iter()
+            .map(|node| {
+                let through =
+                    upstream[&node].saturating_add(downstream.get(&node).copied().unwrap_or(0));
+                (node, total - through)
+            })
+            .collect()
+    }
+
+    /// Returns, for every edge feeding a node processed when processing `root_nodes`, the
+    /// delay, in samples, that must be applied to it's signal to line it up with the latest
+    /// signal arriving at the same node. Edges summed into the same input are reported
+    /// separately.
+    ///
+    /// This crate doesn't compensate latencies itself, this is for runners that do, and UIs.
+    ///
+    /// # Panics
+    ///
+    /// if no node exists for any of the ids in `root_nodes`
+    pub fn alignment_delays(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+    ) -> FnvHashMap<Edge, u64> {
+        let mut upstream = FnvHashMap::default();
+        let mut order = vec![];
+        let mut visited = FnvHashSet::default();
+
+        for root in root_nodes {
+            self.visit_post_order(&root, &mut visited, &mut order);
+        }
+
+        let mut delays = FnvHashMap::default();
+
+        for node in order {
+            let inputs = self[&node].inputs();
+
+            let arrival = inputs
+                .values()
+                .flat_map(|input| input.connections().keys())
+                .map(|source| self.upstream_latency(source, &mut upstream))
+                .max()
+                .unwrap_or(0);
+
+            for (input_id, input) in inputs {
+                for (source, outputs) in input.connections() {
+                    let delay = arrival - self.upstream_latency(source, &mut upstream);
+
+                    for output in outputs {
+                        delays.insert(
+                            (
+                                (source.clone(), output.clone()),
+                                (node.clone(), input_id.clone()),
+                            ),
+                            delay,
+                        );
+                    }
+                }
+            }
+        }
+
+        delays
+    }
+
+    /// Returns how many samples the root nodes keep outputting sound for after the graph's
+    /// input stops, that is, the longest sum of [tails](Node::tail) and latencies along
+    /// any path ending at one of them. `u64::MAX` if it may never decay.
+    ///
+    /// Hosts should keep processing for that long before suspending the graph.
+    ///
+    /// # Panics
+    ///
+    /// if no node exists for any of the ids in `root_nodes`
+    pub fn tail_length(&self, root_nodes: impl IntoIterator<Item = NodeID>) -> u64 {
+        let mut order = vec![];
+        let mut visited = FnvHashSet::default();
+        let roots = Vec::from_iter(root_nodes);
+
+        for root in &roots {
+            self.visit_post_order(root, &mut visited, &mut order);
+        }
+
+        let mut tails = FnvHashMap::<NodeID, u64>::default();
+
+        for node in order {
+            let this_node = &self[&node];
+
+            let input_tail = this_node
+                .inputs()
+                .values()
+                .flat_map(|input| input.connections().keys())
+                .map(|source| tails[source])
+                .max()
+                .unwrap_or(0);
+
+            tails.insert(
+                node,
+                input_tail
+                    .saturating_add(this_node.latency)
+                    .saturating_add(this_node.tail),
+            );
+        }
+
+        roots.iter().map(|root| tails[root]).max().unwrap_or(0)
+    }
+}
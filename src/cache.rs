@@ -0,0 +1,170 @@
+use super::*;
+use core::hash::Hasher;
+use fnv::FnvHasher;
+use std::collections::VecDeque;
+
+impl AudioGraph {
+    /// Returns a hash of the graph's nodes, ports, edges and latencies, which doesn't depend
+    /// on the order of insertion, and is stable across processes.
+    ///
+    /// Input defaults and user data are not included.
+    #[inline]
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+
+        let mut nodes = Vec::from_iter(self.nodes());
+        nodes.sort_unstable_by_key(|&(id, _)| id);
+
+        hasher.write_usize(nodes.len());
+
+        for (id, node) in nodes {
+            id.hash(&mut hasher);
+            node.latency.hash(&mut hasher);
+
+            let mut outputs = Vec::from_iter(node.output_ids());
+            outputs.sort_unstable();
+            outputs.hash(&mut hasher);
+
+            let mut inputs = Vec::from_iter(node.inputs());
+            inputs.sort_unstable_by_key(|&(id, _)| id);
+            hasher.write_usize(inputs.len());
+
+            for (input_id, input) in inputs {
+                input_id.hash(&mut hasher);
+
+                let mut sources =
+                    Vec::from_iter(input.connections().iter().flat_map(|(source, outputs)| {
+                        outputs.iter().map(move |output| (source, output))
+                    }));
+                sources.sort_unstable();
+                sources.hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+}
+
+/// What a cached schedule was compiled from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScheduleKey {
+    /// See [`AudioGraph::content_hash`]
+    pub graph: u64,
+    /// Sorted, without duplicates
+    pub root_nodes: Vec<NodeID>,
+    pub sum_buffers: SumBufferPolicy,
+}
+
+impl ScheduleKey {
+    #[inline]
+    pub fn new(
+        graph: &AudioGraph,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+        sum_buffers: SumBufferPolicy,
+    ) -> Self {
+        let mut root_nodes = Vec::from_iter(root_nodes);
+        root_nodes.sort_unstable();
+        root_nodes.dedup();
+
+        Self {
+            graph: graph.content_hash(),
+            root_nodes,
+            sum_buffers,
+        }
+    }
+}
+
+/// Keeps the most recently used compiled schedules, so that recompiling identical graphs
+/// (e. g. when reloading a preset) is instant.
+///
+/// Graphs are identified by their content hash, collisions, while very unlikely, aren't
+/// detected. Schedules compiled with a custom [`OrderingHeuristic`] shouldn't be cached.
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleCache {
+    // most recently used last
+    entries: VecDeque<(ScheduleKey, (usize, Vec<Task>))>,
+    capacity: usize,
+}
+
+impl ScheduleCache {
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &ScheduleKey) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Returns the number of buffers and the schedule cached for `key`, marking
+    /// them as the most recently used ones.
+    #[inline]
+    pub fn get(&mut self, key: &ScheduleKey) -> Option<(usize, &[Task])> {
+        let i = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(i).unwrap();
+        self.entries.push_back(entry);
+        self.entries
+            .back()
+            .map(|(_, (num_buffers, schedule))| (*num_buffers, schedule.as_slice()))
+    }
+
+    /// Caches `schedule`, using `num_buffers` buffers, for `key`, evicting the least
+    /// recently used schedule if the cache is full.
+    #[inline]
+    pub fn insert(&mut self, key: ScheduleKey, num_buffers: usize, schedule: Vec<Task>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(i) = self.entries.iter().position(|(k, _)| k == &key) {
+            self.entries.remove(i);
+        } else if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back((key, (num_buffers, schedule)));
+    }
+
+    /// Like [`AudioGraph::compile_with`], with only the given sum buffer policy as
+    /// options, but returns the cached schedule, if any, compiling and caching it otherwise.
+    #[inline]
+    pub fn get_or_compile(
+        &mut self,
+        graph: &AudioGraph,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+        sum_buffers: SumBufferPolicy,
+    ) -> (usize, Vec<Task>) {
+        let key = ScheduleKey::new(graph, root_nodes, sum_buffers);
+
+        if let Some((num_buffers, schedule)) = self.get(&key) {
+            return (num_buffers, schedule.to_vec());
+        }
+
+        let (num_buffers, schedule) = graph.compile_with(
+            key.root_nodes.iter().cloned(),
+            CompileOptions::new().sum_buffers(sum_buffers),
+        );
+
+        self.insert(key, num_buffers, schedule.clone());
+        (num_buffers, schedule)
+    }
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+/// An edit made to an [`AudioGraph`], as recorded when change recording is enabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphChange {
+    NodeInserted(NodeID),
+    /// Emitted after the removal of all the edges connected to said node
+    NodeRemoved(NodeID),
+    EdgeInserted {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    EdgeRemoved {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    LatencyChanged {
+        node: NodeID,
+        old: u64,
+        new: u64,
+    },
+}
+
+impl AudioGraph {
+    /// Enables, or disables, the recording of edits made through the graph's methods.
+    ///
+    /// Edits made directly to nodes (through [`Self::get_node_mut`]) are not recorded.
+    /// Disabling recording discards all recorded changes that haven't been drained.
+    #[inline]
+    pub fn record_changes(&mut self, enabled: bool) {
+        match (enabled, &self.changes) {
+            (true, None) => self.changes = Some(vec![]),
+            (false, Some(_)) => self.changes = None,
+            _ => {}
+        }
+    }
+
+    #[inline]
+    pub fn is_recording_changes(&self) -> bool {
+        self.changes.is_some()
+    }
+
+    /// Removes and returns all changes recorded since the last call, in order.
+    ///
+    /// The changes are removed even if the returned iterator is dropped without
+    /// being consumed.
+    #[inline]
+    pub fn drain_changes(&mut self) -> impl Iterator<Item = GraphChange> + '_ {
+        self.changes
+            .as_mut()
+            .map(|changes| changes.drain(..))
+            .into_iter()
+            .flatten()
+    }
+
+    #[inline]
+    pub(crate) fn record_change(&mut self, change: impl FnOnce() -> GraphChange) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change());
+        }
+    }
+}
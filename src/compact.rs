@@ -0,0 +1,194 @@
+use super::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FlatTask {
+    Node {
+        id: NodeID,
+        inputs: Range<usize>,
+        outputs: Range<usize>,
+    },
+    Sum {
+        left: usize,
+        right: usize,
+        output: usize,
+    },
+}
+
+/// A schedule stored in three contiguous arrays (tasks, input ports and output ports),
+/// instead of a couple of hash maps per node task, for audio thread residency.
+///
+/// Ports of a node task are sorted by ID.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactSchedule {
+    num_buffers: usize,
+    tasks: Box<[FlatTask]>,
+    inputs: Box<[(InputID, usize)]>,
+    outputs: Box<[(OutputID, usize)]>,
+}
+
+/// A task of a [`CompactSchedule`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactTask<'a> {
+    Node {
+        id: &'a NodeID,
+        inputs: &'a [(InputID, usize)],
+        outputs: &'a [(OutputID, usize)],
+    },
+    Sum {
+        left: usize,
+        right: usize,
+        output: usize,
+    },
+}
+
+/// Executes the tasks of a [`CompactSchedule`], the counterpart of [`TaskRunner`], with
+/// ports passed as slices, sorted by ID, instead of hash maps.
+pub trait CompactTaskRunner {
+    fn run_node(&mut self, id: &NodeID, inputs: &[(InputID, usize)], outputs: &[(OutputID, usize)]);
+
+    /// See [`TaskRunner::run_sum`].
+    fn run_sum(&mut self, left: usize, right: usize, output: usize);
+}
+
+impl CompactTask<'_> {
+    #[inline]
+    pub fn run(self, runner: &mut (impl CompactTaskRunner + ?Sized)) {
+        match self {
+            Self::Node {
+                id,
+                inputs,
+                outputs,
+            } => runner.run_node(id, inputs, outputs),
+            Self::Sum {
+                left,
+                right,
+                output,
+            } => runner.run_sum(left, right, output),
+        }
+    }
+}
+
+impl CompactSchedule {
+    #[inline]
+    pub fn new(num_buffers: usize, schedule: &[Task]) -> Self {
+        fn append<P: Ord + Clone>(
+            ports: &FnvHashMap<P, usize>,
+            flat: &mut Vec<(P, usize)>,
+        ) -> Range<usize> {
+            let start = flat.len();
+            flat.extend(ports.iter().map(|(port, &buf)| (port.clone(), buf)));
+            flat[start..].sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            start..flat.len()
+        }
+
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+
+        let tasks = schedule
+            .iter()
+            .map(|task| match task {
+                Task::Node {
+                    id,
+                    inputs: node_inputs,
+                    outputs: node_outputs,
+                } => FlatTask::Node {
+                    id: id.clone(),
+                    inputs: append(node_inputs, &mut inputs),
+                    outputs: append(node_outputs, &mut outputs),
+                },
+                &Task::Sum {
+                    left,
+                    right,
+                    output,
+                } => FlatTask::Sum {
+                    left,
+                    right,
+                    output,
+                },
+            })
+            .collect();
+
+        Self {
+            num_buffers,
+            tasks,
+            inputs: inputs.into(),
+            outputs: outputs.into(),
+        }
+    }
+
+    #[inline]
+    pub fn num_buffers(&self) -> usize {
+        self.num_buffers
+    }
+
+    /// The number of tasks.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<CompactTask<'_>> {
+        self.tasks.get(index).map(|task| self.view(task))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = CompactTask<'_>> {
+        self.tasks.iter().map(|task| self.view(task))
+    }
+
+    fn view<'a>(&'a self, task: &'a FlatTask) -> CompactTask<'a> {
+        match task {
+            FlatTask::Node {
+                id,
+                inputs,
+                outputs,
+            } => CompactTask::Node {
+                id,
+                inputs: &self.inputs[inputs.clone()],
+                outputs: &self.outputs[outputs.clone()],
+            },
+            &FlatTask::Sum {
+                left,
+                right,
+                output,
+            } => CompactTask::Sum {
+                left,
+                right,
+                output,
+            },
+        }
+    }
+
+    /// Runs every task, in order, for one block. Doesn't allocate.
+    #[inline]
+    pub fn run(&self, runner: &mut (impl CompactTaskRunner + ?Sized)) {
+        for task in self.iter() {
+            task.run(runner);
+        }
+    }
+
+    /// Converts back to the regular representation.
+    #[inline]
+    pub fn to_schedule(&self) -> Vec<Task> {
+        self.iter()
+            .map(|task| match task {
+                CompactTask::Node {
+                    id,
+                    inputs,
+                    outputs,
+                } => Task::node(id.clone(), inputs.iter().cloned(), outputs.iter().cloned()),
+                CompactTask::Sum {
+                    left,
+                    right,
+                    output,
+                } => Task::sum(left, right, output),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,152 @@
+use super::*;
+
+/// A [`TaskRunner`] for [`ConditionalSchedule`]s.
+pub trait GatedTaskRunner: TaskRunner {
+    /// Called right after the task of the gate node `gate` has run, returns whether
+    /// the tasks it gates should run in the current block.
+    fn is_open(&mut self, gate: &NodeID) -> bool;
+
+    /// Fill buffer `buffer` with silence.
+    fn silence(&mut self, buffer: usize);
+}
+
+/// A compiled schedule in which gate nodes can, every block, skip a pre-declared set
+/// of node tasks (e. g. an effect chain after a noise gate), which write silence to
+/// their outputs instead. Saves running them without recompiling the schedule.
+///
+/// Gated nodes are assumed to output silence when skipped, which, for nodes with tails
+/// (reverbs, delays...), is only true once the tail has decayed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConditionalSchedule {
+    schedule: Vec<Task>,
+    gates: Vec<NodeID>,
+    // for every task, the indices, in `gates`, of the gates it's skipped by
+    gated_by: Vec<Vec<usize>>,
+    // for every task, the index, in `gates`, of the gate it's the task of, if any
+    gate_of: Vec<Option<usize>>,
+    open: Vec<bool>,
+}
+
+impl ConditionalSchedule {
+    #[inline]
+    pub fn new(schedule: Vec<Task>) -> Self {
+        Self {
+            gated_by: vec![vec![]; schedule.len()],
+            gate_of: vec![None; schedule.len()],
+            schedule,
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn schedule(&self) -> &[Task] {
+        &self.schedule
+    }
+
+    #[inline]
+    pub fn gates(&self) -> &[NodeID] {
+        &self.gates
+    }
+
+    /// Makes the tasks of `nodes` skippable by `gate`. Returns `false`, leaving the
+    /// schedule unchanged, if any of these nodes isn't in the schedule, or any of
+    /// `nodes` is scheduled before `gate`.
+    ///
+    /// A node can be gated by multiple gates, in which case it's skipped if any of
+    /// them is closed. A gate skipped by another gate is considered closed.
+    #[inline]
+    pub fn gate(&mut self, gate: &NodeID, nodes: impl IntoIterator<Item = NodeID>) -> bool {
+        let task_of = |node: &NodeID| {
+            self.schedule
+                .iter()
+                .position(|task| matches!(task, Task::Node { id, .. } if id == node))
+        };
+
+        let Some(gate_task) = task_of(gate) else {
+            return false;
+        };
+
+        let Some(tasks) = nodes
+            .into_iter()
+            .map(|node| task_of(&node).filter(|&task| task > gate_task))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+
+        let gate_index = *self.gate_of[gate_task].get_or_insert_with(|| {
+            self.gates.push(gate.clone());
+            self.open.push(true);
+            self.gates.len() - 1
+        });
+
+        for task in tasks {
+            let gated_by = &mut self.gated_by[task];
+
+            if !gated_by.contains(&gate_index) {
+                gated_by.push(gate_index);
+            }
+        }
+
+        true
+    }
+
+    /// Returns the schedule, and the hook skipping it's gated tasks, to run it along
+    /// with other hooks, through [`run_schedule_with`].
+    #[inline]
+    pub fn hook(&mut self) -> (&[Task], GateHook<'_>) {
+        let hook = GateHook {
+            gated_by: &self.gated_by,
+            gate_of: &self.gate_of,
+            open: &mut self.open,
+        };
+
+        (&self.schedule, hook)
+    }
+
+    /// Runs every task, in order, for one block. Doesn't allocate.
+    #[inline]
+    pub fn run(&mut self, runner: &mut (impl GatedTaskRunner + ?Sized)) {
+        let (schedule, mut hook) = self.hook();
+        run_schedule_with(schedule, runner, &mut hook);
+    }
+}
+
+/// The [`TaskHook`] of a [`ConditionalSchedule`], see [`ConditionalSchedule::hook`].
+///
+/// Gates are reopened before the first task of every block. A gate skipped by any
+/// hook is considered closed.
+#[derive(Debug)]
+pub struct GateHook<'a> {
+    gated_by: &'a [Vec<usize>],
+    gate_of: &'a [Option<usize>],
+    open: &'a mut [bool],
+}
+
+impl<R: GatedTaskRunner + ?Sized> TaskHook<R> for GateHook<'_> {
+    #[inline]
+    fn before(&mut self, index: usize, task: &Task, runner: &mut R) -> bool {
+        if index == 0 {
+            self.open.fill(true);
+        }
+
+        let skipped = self.gated_by[index].iter().any(|&gate| !self.open[gate]);
+
+        if skipped {
+            if let Task::Node { outputs, .. } = task {
+                for &buffer in outputs.values() {
+                    runner.silence(buffer);
+                }
+            }
+        }
+
+        !skipped
+    }
+
+    #[inline]
+    fn after(&mut self, index: usize, task: &Task, ran: bool, runner: &mut R) {
+        if let (Some(gate), Task::Node { id, .. }) = (self.gate_of[index], task) {
+            self.open[gate] = ran && runner.is_open(id);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+use core::ops::{Add, AddAssign};
+
+/// Cost of running a task, in arbitrary (but consistent) time units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cost {
+    /// Paid once per call, regardless of the number of samples processed
+    pub fixed: f64,
+    pub per_sample: f64,
+}
+
+impl Cost {
+    #[inline]
+    pub fn new(fixed: f64, per_sample: f64) -> Self {
+        Self { fixed, per_sample }
+    }
+
+    #[inline]
+    pub fn for_block(&self, block_size: usize) -> f64 {
+        self.fixed + self.per_sample * block_size as f64
+    }
+}
+
+impl Add for Cost {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.fixed + rhs.fixed, self.per_sample + rhs.per_sample)
+    }
+}
+
+impl AddAssign for Cost {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// Expected cost of running a schedule, broken down by task type.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScheduleCost {
+    pub nodes: Cost,
+    pub sums: Cost,
+}
+
+impl ScheduleCost {
+    #[inline]
+    pub fn new<'a>(
+        schedule: impl IntoIterator<Item = &'a Task>,
+        mut node_cost: impl FnMut(&NodeID) -> Cost,
+        sum_cost: Cost,
+    ) -> Self {
+        let mut cost = Self::default();
+
+        for task in schedule {
+            match task {
+                Task::Node { id, .. } => cost.nodes += node_cost(id),
+                Task::Sum { .. } => cost.sums += sum_cost,
+            }
+        }
+
+        cost
+    }
+
+    #[inline]
+    pub fn total(&self) -> Cost {
+        self.nodes + self.sums
+    }
+
+    #[inline]
+    pub fn block_cost(&self, block_size: usize) -> f64 {
+        self.total().for_block(block_size)
+    }
+
+    /// Returns the smallest block size the schedule can be run with while keeping up with
+    /// real time, given the duration of a sample in the same units as the costs, or `None`
+    /// if the schedule can't keep up at any block size.
+    ///
+    /// Splitting host blocks into chunks at least this large still fits the deadline.
+    #[inline]
+    pub fn min_block_size(&self, sample_period: f64) -> Option<usize> {
+        let Cost { fixed, per_sample } = self.total();
+
+        let headroom = sample_period - per_sample;
+
+        (headroom > 0.).then(|| ((fixed / headroom).ceil() as usize).max(1))
+    }
+}
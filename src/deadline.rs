@@ -0,0 +1,158 @@
+use super::*;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A block that took longer to process than it's real-time deadline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeadlineMiss {
+    /// Index of the block, counting from the first one run through the monitor
+    pub block: u64,
+    pub elapsed: Duration,
+    pub deadline: Duration,
+    /// Index, in the schedule, of the task that was running when the deadline passed
+    pub task: usize,
+}
+
+/// Runs schedules, measuring each block's processing time against it's deadline
+/// (`block_size / sample_rate`), and keeps the most recent misses.
+///
+/// The sample rate is validated once, when creating the monitor, so that running
+/// blocks never panics.
+///
+/// Every task is timed, which has a (small) cost of it's own.
+#[derive(Clone, Debug)]
+pub struct DeadlineMonitor {
+    misses: VecDeque<DeadlineMiss>,
+    capacity: usize,
+    sample_rate: f64,
+    blocks: u64,
+}
+
+impl DeadlineMonitor {
+    /// Creates a monitor for blocks processed at `sample_rate`, remembering up to
+    /// `capacity` misses. Older misses are discarded first. No allocations are made
+    /// after this call.
+    ///
+    /// # Panics
+    ///
+    /// if `sample_rate` isn't positive and finite
+    #[inline]
+    pub fn new(capacity: usize, sample_rate: f64) -> Self {
+        assert!(
+            sample_rate.is_finite() && sample_rate > 0.,
+            "sample rates must be positive and finite, got {sample_rate}"
+        );
+
+        Self {
+            misses: VecDeque::with_capacity(capacity),
+            capacity,
+            sample_rate,
+            blocks: 0,
+        }
+    }
+
+    #[inline]
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Returns a hook timing the current block, of `block_size` samples, to run it along
+    /// with other hooks, through [`run_schedule_with`]. The block is timed from this call
+    /// to [`DeadlineHook::finish`].
+    #[inline]
+    pub fn hook(&mut self, block_size: usize) -> DeadlineHook<'_> {
+        let block = self.blocks;
+        self.blocks += 1;
+
+        // deadlines too long to be represented are never missed
+        let deadline = Duration::try_from_secs_f64(block_size as f64 / self.sample_rate)
+            .unwrap_or(Duration::MAX);
+
+        DeadlineHook {
+            deadline,
+            block,
+            start: Instant::now(),
+            late_task: None,
+            monitor: self,
+        }
+    }
+
+    /// Runs `schedule` for one block, returns `false` if the deadline was missed.
+    #[inline]
+    pub fn run_block(
+        &mut self,
+        schedule: &[Task],
+        runner: &mut (impl TaskRunner + ?Sized),
+        block_size: usize,
+    ) -> bool {
+        let mut hook = self.hook(block_size);
+        run_schedule_with(schedule, runner, &mut hook);
+        hook.finish()
+    }
+
+    /// Recorded misses, oldest first.
+    #[inline]
+    pub fn misses(&self) -> impl Iterator<Item = &DeadlineMiss> {
+        self.misses.iter()
+    }
+
+    #[inline]
+    pub fn blocks_run(&self) -> u64 {
+        self.blocks
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.misses.clear();
+    }
+}
+
+/// The [`TaskHook`] of a [`DeadlineMonitor`], see [`DeadlineMonitor::hook`].
+#[derive(Debug)]
+pub struct DeadlineHook<'a> {
+    monitor: &'a mut DeadlineMonitor,
+    block: u64,
+    deadline: Duration,
+    start: Instant,
+    late_task: Option<usize>,
+}
+
+impl DeadlineHook<'_> {
+    /// Ends the block, recording it's miss, if any. Returns `false` if the deadline was missed.
+    #[inline]
+    pub fn finish(self) -> bool {
+        let Some(task) = self.late_task else {
+            return true;
+        };
+
+        let monitor = self.monitor;
+
+        if monitor.capacity == 0 {
+            return false;
+        }
+
+        if monitor.misses.len() == monitor.capacity {
+            monitor.misses.pop_front();
+        }
+
+        monitor.misses.push_back(DeadlineMiss {
+            block: self.block,
+            elapsed: self.start.elapsed(),
+            deadline: self.deadline,
+            task,
+        });
+
+        false
+    }
+}
+
+impl<R: ?Sized> TaskHook<R> for DeadlineHook<'_> {
+    #[inline]
+    fn after(&mut self, index: usize, _task: &Task, _ran: bool, _runner: &mut R) {
+        if self.late_task.is_none() && self.start.elapsed() > self.deadline {
+            self.late_task = Some(index);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use super::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// An edit, queued from any thread through a [`GraphEditor`]'s command sender.
+#[derive(Debug)]
+pub enum GraphEdit {
+    InsertNode(Node),
+    RemoveNode(NodeID),
+    InsertEdge {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    RemoveEdge {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    SetLatency {
+        node: NodeID,
+        latency: u64,
+    },
+}
+
+/// The result of applying a [`GraphEdit`], as returned by the corresponding [`AudioGraph`] method.
+#[derive(Debug)]
+pub enum EditOutcome {
+    NodeInserted(NodeID),
+    NodeRemoved(Option<Node>),
+    EdgeInserted(Result<bool, bool>),
+    EdgeRemoved(bool),
+    LatencySet(Option<u64>),
+}
+
+/// Owns a graph, applying edits queued from any thread, and recompiling it
+/// on a [`CompileService`] when they change it.
+pub struct GraphEditor {
+    graph: AudioGraph,
+    root_nodes: Vec<NodeID>,
+    commands: Receiver<GraphEdit>,
+    command_sender: Sender<GraphEdit>,
+    outcomes: Sender<EditOutcome>,
+    compiler: CompileService,
+}
+
+impl GraphEditor {
+    /// Returns the editor, and the receiving end of the channel through which the outcome
+    /// of every edit is reported, in order.
+    #[inline]
+    pub fn new(
+        mut graph: AudioGraph,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+    ) -> (Self, Receiver<EditOutcome>) {
+        let (command_sender, commands) = mpsc::channel();
+        let (outcomes, outcome_receiver) = mpsc::channel();
+
+        graph.record_changes(true);
+        let _ = graph.drain_changes();
+
+        let editor = Self {
+            graph,
+            root_nodes: Vec::from_iter(root_nodes),
+            commands,
+            command_sender,
+            outcomes,
+            compiler: CompileService::new(),
+        };
+
+        (editor, outcome_receiver)
+    }
+
+    /// Returns a sender through which edits can be queued from any thread.
+    #[inline]
+    pub fn command_sender(&self) -> Sender<GraphEdit> {
+        self.command_sender.clone()
+    }
+
+    #[inline]
+    pub fn graph(&self) -> &AudioGraph {
+        &self.graph
+    }
+
+    /// The compile service delivering the schedules of the edited graph.
+    #[inline]
+    pub fn compiler(&self) -> &CompileService {
+        &self.compiler
+    }
+
+    /// The nodes that future compilations will process. Removed nodes are removed
+    /// from these too.
+    #[inline]
+    pub fn root_nodes(&self) -> &[NodeID] {
+        &self.root_nodes
+    }
+
+    /// Sets the nodes that future compilations will process.
+    /// This doesn't trigger recompilation on it's own.
+    #[inline]
+    pub fn set_root_nodes(&mut self, root_nodes: impl IntoIterator<Item = NodeID>) {
+        self.root_nodes = Vec::from_iter(root_nodes);
+    }
+
+    /// Requests the compilation of the current graph, returning the request's id.
+    #[inline]
+    pub fn recompile(&mut self) -> Result<u64, CompileError> {
+        self.compiler
+            .request(self.graph.snapshot(), self.root_nodes.iter().cloned())
+    }
+
+    /// Applies all queued edits, in order, requesting a recompilation if any of them changed
+    /// the graph. Returns the compilation request's id, if any.
+    #[inline]
+    pub fn apply_pending(&mut self) -> Result<Option<u64>, CompileError> {
+        while let Ok(edit) = self.commands.try_recv() {
+            let outcome = match edit {
+                GraphEdit::InsertNode(node) => {
+                    EditOutcome::NodeInserted(self.graph.insert_node(node))
+                }
+                GraphEdit::RemoveNode(id) => {
+                    let removed = self.graph.remove_node(&id);
+
+                    // otherwise, compiling would panic, and a node inserted later with the
+                    // same id would become a root
+                    if removed.is_some() {
+                        self.root_nodes.retain(|root| root != &id);
+                    }
+
+                    EditOutcome::NodeRemoved(removed)
+                }
+                GraphEdit::InsertEdge { from, to } => {
+                    EditOutcome::EdgeInserted(self.graph.try_insert_edge(from, to))
+                }
+                GraphEdit::RemoveEdge { from, to } => {
+                    EditOutcome::EdgeRemoved(self.graph.remove_edge(from, to))
+                }
+                GraphEdit::SetLatency { node, latency } => {
+                    EditOutcome::LatencySet(self.graph.set_latency(&node, latency))
+                }
+            };
+
+            // the receiver may have been dropped, if the caller isn't interested in outcomes
+            let _ = self.outcomes.send(outcome);
+        }
+
+        (self.graph.drain_changes().count() > 0)
+            .then(|| self.recompile())
+            .transpose()
+    }
+}
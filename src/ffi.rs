@@ -0,0 +1,282 @@
+//! C ABI for driving graphs and schedules from non-Rust hosts.
+//!
+//! Graphs and schedules are opaque handles, created and destroyed through this module.
+//! Nodes created here have their input and output ports numbered from `0`. Buffers are
+//! owned by the host, which runs schedules through a [`PolygraphRunner`].
+//!
+//! The `polygraph-ffi` crate (in `ffi/`) builds this module as a shared and static library.
+
+use super::*;
+use core::{ffi::c_void, ptr, slice};
+
+/// Opaque graph handle.
+pub struct PolygraphGraph(AudioGraph);
+
+/// Opaque compiled schedule handle.
+pub struct PolygraphSchedule {
+    num_buffers: usize,
+    tasks: Vec<FfiTask>,
+}
+
+/// A port of a node, and the buffer it reads from, or writes to.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PolygraphPortBuffer {
+    pub port: u32,
+    pub buffer: usize,
+}
+
+/// Callbacks running the tasks of a schedule, see [`TaskRunner`].
+#[repr(C)]
+pub struct PolygraphRunner {
+    pub user_data: *mut c_void,
+    pub run_node: extern "C" fn(
+        user_data: *mut c_void,
+        node: u32,
+        inputs: *const PolygraphPortBuffer,
+        num_inputs: usize,
+        outputs: *const PolygraphPortBuffer,
+        num_outputs: usize,
+    ),
+    /// `output` may alias `left` or `right`
+    pub run_sum: extern "C" fn(user_data: *mut c_void, left: usize, right: usize, output: usize),
+}
+
+// port lists are flattened ahead of time, so that running a schedule doesn't allocate
+enum FfiTask {
+    Node {
+        id: u32,
+        inputs: Vec<PolygraphPortBuffer>,
+        outputs: Vec<PolygraphPortBuffer>,
+    },
+    Sum {
+        left: usize,
+        right: usize,
+        output: usize,
+    },
+}
+
+impl From<&Task> for FfiTask {
+    fn from(task: &Task) -> Self {
+        fn ports<P>(
+            ports: &FnvHashMap<P, usize>,
+            id: impl Fn(&P) -> u32,
+        ) -> Vec<PolygraphPortBuffer> {
+            let mut ports =
+                Vec::from_iter(ports.iter().map(|(port, &buffer)| PolygraphPortBuffer {
+                    port: id(port),
+                    buffer,
+                }));
+            ports.sort_unstable_by_key(|port| port.port);
+            ports
+        }
+
+        match task {
+            Task::Node {
+                id,
+                inputs,
+                outputs,
+            } => Self::Node {
+                id: id.0,
+                inputs: ports(inputs, |input| input.0),
+                outputs: ports(outputs, |output| output.0),
+            },
+            &Task::Sum {
+                left,
+                right,
+                output,
+            } => Self::Sum {
+                left,
+                right,
+                output,
+            },
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn polygraph_graph_new() -> *mut PolygraphGraph {
+    Box::into_raw(Box::new(PolygraphGraph(AudioGraph::default())))
+}
+
+/// # Safety
+///
+/// `graph` must be null, or have been returned by [`polygraph_graph_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_free(graph: *mut PolygraphGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// Inserts a node with `num_inputs` input ports and `num_outputs` output ports,
+/// returns it's ID.
+///
+/// # Safety
+///
+/// `graph` must be a valid graph handle.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_add_node(
+    graph: *mut PolygraphGraph,
+    num_inputs: usize,
+    num_outputs: usize,
+    latency: u64,
+) -> u32 {
+    let mut node = Node::default();
+    node.add_input_bus(num_inputs);
+    node.add_output_bus(num_outputs);
+    node.latency = latency;
+
+    (*graph).0.insert_node(node).0
+}
+
+/// Returns whether a node existed, and was removed, at `node`.
+///
+/// # Safety
+///
+/// `graph` must be a valid graph handle.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_remove_node(
+    graph: *mut PolygraphGraph,
+    node: u32,
+) -> bool {
+    (*graph).0.remove_node(&NodeID(node)).is_some()
+}
+
+/// Returns `1` if the edge was inserted, `0` if it already existed, `-1` if it would
+/// have created a cycle, and `-2` if either of the ports doesn't exist.
+///
+/// # Safety
+///
+/// `graph` must be a valid graph handle.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_add_edge(
+    graph: *mut PolygraphGraph,
+    from: u32,
+    output: u32,
+    to: u32,
+    input: u32,
+) -> i32 {
+    match (*graph).0.try_insert_edge(
+        (NodeID(from), OutputID(output)),
+        (NodeID(to), InputID(input)),
+    ) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(true) => -1,
+        Err(false) => -2,
+    }
+}
+
+/// Returns whether the edge existed, and was removed.
+///
+/// # Safety
+///
+/// `graph` must be a valid graph handle.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_remove_edge(
+    graph: *mut PolygraphGraph,
+    from: u32,
+    output: u32,
+    to: u32,
+    input: u32,
+) -> bool {
+    (*graph).0.remove_edge(
+        (NodeID(from), OutputID(output)),
+        (NodeID(to), InputID(input)),
+    )
+}
+
+/// Compiles the graph for the `num_roots` root nodes in `roots`. Returns null if any
+/// of them doesn't exist.
+///
+/// # Safety
+///
+/// `graph` must be a valid graph handle, and `roots` must point to `num_roots`
+/// node IDs (or be null if `num_roots` is `0`).
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_graph_compile(
+    graph: *const PolygraphGraph,
+    roots: *const u32,
+    num_roots: usize,
+) -> *mut PolygraphSchedule {
+    let graph = &(*graph).0;
+
+    let roots = if num_roots == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(roots, num_roots)
+    };
+
+    if roots
+        .iter()
+        .any(|&root| graph.get_node(&NodeID(root)).is_none())
+    {
+        return ptr::null_mut();
+    }
+
+    let (num_buffers, schedule) = graph.compile(roots.iter().copied().map(NodeID));
+
+    Box::into_raw(Box::new(PolygraphSchedule {
+        num_buffers,
+        tasks: schedule.iter().map(FfiTask::from).collect(),
+    }))
+}
+
+/// # Safety
+///
+/// `schedule` must be null, or have been returned by [`polygraph_graph_compile`]
+/// and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_schedule_free(schedule: *mut PolygraphSchedule) {
+    if !schedule.is_null() {
+        drop(Box::from_raw(schedule));
+    }
+}
+
+/// Returns the number of buffers the host must provide to run `schedule`.
+///
+/// # Safety
+///
+/// `schedule` must be a valid schedule handle.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_schedule_num_buffers(
+    schedule: *const PolygraphSchedule,
+) -> usize {
+    (*schedule).num_buffers
+}
+
+/// Runs every task of `schedule`, in order, through `runner`'s callbacks. Doesn't allocate.
+///
+/// # Safety
+///
+/// `schedule` must be a valid schedule handle, and `runner` must point to a valid runner.
+#[no_mangle]
+pub unsafe extern "C" fn polygraph_schedule_run(
+    schedule: *const PolygraphSchedule,
+    runner: *const PolygraphRunner,
+) {
+    let runner = &*runner;
+
+    for task in &(*schedule).tasks {
+        match task {
+            FfiTask::Node {
+                id,
+                inputs,
+                outputs,
+            } => (runner.run_node)(
+                runner.user_data,
+                *id,
+                inputs.as_ptr(),
+                inputs.len(),
+                outputs.as_ptr(),
+                outputs.len(),
+            ),
+            &FfiTask::Sum {
+                left,
+                right,
+                output,
+            } => (runner.run_sum)(runner.user_data, left, right, output),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Harness code for the fuzz targets in `fuzz/`.
+
+use super::*;
+
+fn sorted<T: Ord + Clone>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut items = Vec::from_iter(items);
+    items.sort_unstable();
+    items
+}
+
+fn pick<T: Clone>(items: &[T], byte: u8) -> Option<T> {
+    (!items.is_empty()).then(|| items[byte as usize % items.len()].clone())
+}
+
+/// Interprets `data` as a sequence of graph edits and compilations,
+/// panicking if any of the compiled schedules is invalid.
+pub fn graph_ops(data: &[u8]) {
+    let mut graph = AudioGraph::default();
+    let mut bytes = data.iter().copied();
+
+    while bytes.len() != 0 {
+        // ops that can't be applied (e.g. on an empty graph) are skipped
+        let _ = run_op(&mut graph, &mut bytes);
+        check_topological_order(&graph);
+    }
+}
+
+/// Panics if [`AudioGraph::topological_order`] isn't a valid topological order of `graph`.
+pub(crate) fn check_topological_order(graph: &AudioGraph) {
+    let ranks = FnvHashMap::from_iter(graph.topological_order().enumerate().map(|(i, id)| (id, i)));
+
+    assert_eq!(ranks.len(), graph.nodes().count(), "graph: {graph:#?}");
+
+    for (id, node) in graph.nodes() {
+        for source in node
+            .inputs()
+            .values()
+            .flat_map(|input| input.connections().keys())
+        {
+            assert!(ranks[source] < ranks[id], "graph: {graph:#?}");
+        }
+    }
+}
+
+fn run_op(graph: &mut AudioGraph, bytes: &mut impl Iterator<Item = u8>) -> Option<()> {
+    let op = bytes.next()?;
+    let ids = sorted(graph.nodes().map(|(id, _)| id.clone()));
+
+    match op % 6 {
+        0 => {
+            let mut node = Node::default();
+            node.add_input_bus(bytes.next()? as usize % 4);
+            node.add_output_bus(bytes.next()? as usize % 4);
+            graph.insert_node(node);
+        }
+        1 => {
+            let id = pick(&ids, bytes.next()?)?;
+            graph.remove_node(&id)?;
+        }
+        2 | 3 => {
+            let from = pick(&ids, bytes.next()?)?;
+            let output = pick(&sorted(graph[&from].output_ids().clone()), bytes.next()?)?;
+            let to = pick(&ids, bytes.next()?)?;
+            let input = pick(&sorted(graph[&to].inputs().keys().cloned()), bytes.next()?)?;
+
+            if op % 6 == 2 {
+                let edge = ((from, output), (to, input));
+                let batched = graph.clone().try_insert_edges([edge.clone()]);
+                let inserted = graph.try_insert_edge(edge.0, edge.1);
+                assert_eq!(batched, inserted.map(usize::from));
+            } else {
+                graph.remove_edge((from, output), (to, input));
+            }
+        }
+        4 => {
+            let id = pick(&ids, bytes.next()?)?;
+            graph.set_latency(&id, bytes.next()?.into());
+        }
+        _ => {
+            let mask = bytes.next()?;
+            let roots = Vec::from_iter(
+                ids.into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << (i % 8)) != 0)
+                    .map(|(_, id)| id),
+            );
+
+            let (num_buffers, schedule) = graph.compile(roots.iter().cloned());
+            let (reordered_num_buffers, mut reordered) = graph.compile_with(
+                roots.iter().cloned(),
+                CompileOptions::new().ordering(MinimizeLiveBuffers),
+            );
+            remap_buffers(&schedule, &mut reordered, reordered_num_buffers);
+
+            for (num_buffers, schedule) in
+                [(num_buffers, schedule), (reordered_num_buffers, reordered)]
+            {
+                if let Err(e) =
+                    validate_schedule(graph, roots.iter().cloned(), num_buffers, &schedule)
+                {
+                    panic!(
+                        "invalid schedule: {e:?}\ngraph: {graph:#?}\nschedule:\n{}",
+                        pretty_schedule(&schedule)
+                    );
+                }
+            }
+
+            let (latency, path) = graph.critical_path(roots.iter().cloned());
+            assert_eq!(
+                latency,
+                path.iter().map(|id| graph[id].latency).sum::<u64>()
+            );
+
+            let slack = graph.latency_slack(roots.iter().cloned());
+            assert!(path.iter().all(|id| slack[id] == 0));
+        }
+    }
+
+    Some(())
+}
@@ -0,0 +1,313 @@
+use super::*;
+use std::collections::VecDeque;
+
+#[derive(Clone, Debug)]
+enum Op {
+    /// `node` has no connections
+    InsertNode {
+        id: NodeID,
+        node: Node,
+    },
+    /// `node` has no connections
+    RemoveNode {
+        id: NodeID,
+        node: Node,
+    },
+    InsertEdge {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    RemoveEdge {
+        from: (NodeID, OutputID),
+        to: (NodeID, InputID),
+    },
+    SetLatency {
+        node: NodeID,
+        old: u64,
+        new: u64,
+    },
+}
+
+impl Op {
+    fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::InsertNode { id, node } => Self::RemoveNode { id, node },
+            Self::RemoveNode { id, node } => Self::InsertNode { id, node },
+            Self::InsertEdge { from, to } => Self::RemoveEdge { from, to },
+            Self::RemoveEdge { from, to } => Self::InsertEdge { from, to },
+            Self::SetLatency { node, old, new } => Self::SetLatency {
+                node,
+                old: new,
+                new: old,
+            },
+        }
+    }
+
+    fn apply(&self, graph: &mut AudioGraph) {
+        match self.clone() {
+            Self::InsertNode { id, node } => {
+                assert!(
+                    graph.try_insert_node(id.clone(), node).is_ok(),
+                    "INTERNAL ERROR: node id must be free when replaying history"
+                );
+                graph.record_change(|| GraphChange::NodeInserted(id));
+            }
+            Self::RemoveNode { id, .. } => {
+                graph
+                    .remove_node(&id)
+                    .expect("INTERNAL ERROR: node must exist when replaying history");
+            }
+            Self::InsertEdge { from, to } => {
+                assert!(
+                    graph.try_insert_edge(from, to).is_ok_and(|new| new),
+                    "INTERNAL ERROR: edge must be insertable when replaying history"
+                );
+            }
+            Self::RemoveEdge { from, to } => {
+                assert!(
+                    graph.remove_edge(from, to),
+                    "INTERNAL ERROR: edge must exist when replaying history"
+                );
+            }
+            Self::SetLatency { node, new, .. } => {
+                graph
+                    .set_latency(&node, new)
+                    .expect("INTERNAL ERROR: node must exist when replaying history");
+            }
+        }
+    }
+}
+
+/// Applies edits to a graph while recording them, so that they can be undone and redone.
+///
+/// Every edit is it's own undo step, unless made inside a transaction, in which case all edits
+/// made until the transaction is committed form one undo step. Consecutive latency changes
+/// on the same node are merged into one step, until [`Self::seal`] is called.
+///
+/// Edits made to the graph bypassing the history make undoing and redoing panic.
+#[derive(Clone, Debug)]
+pub struct EditHistory {
+    undo: VecDeque<Vec<Op>>,
+    redo: Vec<Vec<Op>>,
+    transaction: Option<Vec<Op>>,
+    max_depth: usize,
+    sealed: bool,
+}
+
+impl Default for EditHistory {
+    #[inline]
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+impl EditHistory {
+    /// Creates a history holding at most `max_depth` undo steps.
+    #[inline]
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            transaction: None,
+            max_depth,
+            sealed: true,
+        }
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Prevents the next edit from being merged with the previous one.
+    #[inline]
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Groups all edits made until the next call to [`Self::commit_transaction`] into one
+    /// undo step. Does nothing if a transaction is already in progress.
+    #[inline]
+    pub fn begin_transaction(&mut self) {
+        self.transaction.get_or_insert_with(Vec::new);
+    }
+
+    #[inline]
+    pub fn commit_transaction(&mut self) {
+        if let Some(ops) = self.transaction.take() {
+            self.push_step(ops);
+            self.seal();
+        }
+    }
+
+    fn push_step(&mut self, ops: Vec<Op>) {
+        if ops.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+
+        if let (false, [Op::SetLatency { node, new, .. }]) = (self.sealed, ops.as_slice()) {
+            if let Some(
+                [Op::SetLatency {
+                    node: last_node,
+                    new: last_new,
+                    ..
+                }],
+            ) = self.undo.back_mut().map(Vec::as_mut_slice)
+            {
+                if last_node == node {
+                    *last_new = *new;
+                    return;
+                }
+            }
+        }
+
+        if self.max_depth == 0 {
+            return;
+        }
+
+        if self.undo.len() == self.max_depth {
+            self.undo.pop_front();
+        }
+
+        self.sealed = false;
+        self.undo.push_back(ops);
+    }
+
+    /// Applies `edit` to `graph`, recording it in the history if it changed the graph.
+    #[inline]
+    pub fn apply(&mut self, graph: &mut AudioGraph, edit: GraphEdit) -> EditOutcome {
+        let mut ops = vec![];
+
+        let outcome = match edit {
+            GraphEdit::InsertNode(node) => {
+                let id = graph.insert_node(node);
+                let node = graph[&id].clone();
+                ops.push(Op::InsertNode {
+                    id: id.clone(),
+                    node,
+                });
+                EditOutcome::NodeInserted(id)
+            }
+            GraphEdit::RemoveNode(id) => {
+                for (node_id, node) in &graph.nodes {
+                    for (input_id, input) in node.inputs() {
+                        for output_id in input.connections().get(&id).into_iter().flatten() {
+                            ops.push(Op::RemoveEdge {
+                                from: (id.clone(), output_id.clone()),
+                                to: (node_id.clone(), input_id.clone()),
+                            });
+                        }
+                    }
+                }
+
+                for (input_id, input) in graph.get_node(&id).map(Node::inputs).into_iter().flatten()
+                {
+                    for (node_id, output_ids) in input.connections() {
+                        for output_id in output_ids {
+                            ops.push(Op::RemoveEdge {
+                                from: (node_id.clone(), output_id.clone()),
+                                to: (id.clone(), input_id.clone()),
+                            });
+                        }
+                    }
+                }
+
+                let node = graph.remove_node(&id);
+
+                if let Some(node) = &node {
+                    ops.push(Op::RemoveNode {
+                        id,
+                        node: node.clone(),
+                    });
+                }
+
+                EditOutcome::NodeRemoved(node)
+            }
+            GraphEdit::InsertEdge { from, to } => {
+                let res = graph.try_insert_edge(from.clone(), to.clone());
+
+                if res == Ok(true) {
+                    ops.push(Op::InsertEdge { from, to });
+                }
+
+                EditOutcome::EdgeInserted(res)
+            }
+            GraphEdit::RemoveEdge { from, to } => {
+                let removed = graph.remove_edge(from.clone(), to.clone());
+
+                if removed {
+                    ops.push(Op::RemoveEdge { from, to });
+                }
+
+                EditOutcome::EdgeRemoved(removed)
+            }
+            GraphEdit::SetLatency { node, latency } => {
+                let old = graph.set_latency(&node, latency);
+
+                if let Some(old) = old.filter(|&old| old != latency) {
+                    ops.push(Op::SetLatency {
+                        node,
+                        old,
+                        new: latency,
+                    });
+                }
+
+                EditOutcome::LatencySet(old)
+            }
+        };
+
+        match &mut self.transaction {
+            Some(transaction) => transaction.extend(ops),
+            None => self.push_step(ops),
+        }
+
+        outcome
+    }
+
+    /// Undoes the last undo step, returning whether there was one.
+    ///
+    /// Commits any transaction in progress first.
+    #[inline]
+    pub fn undo(&mut self, graph: &mut AudioGraph) -> bool {
+        self.commit_transaction();
+
+        let Some(ops) = self.undo.pop_back() else {
+            return false;
+        };
+
+        for op in ops.iter().rev() {
+            op.inverse().apply(graph);
+        }
+
+        self.redo.push(ops);
+        self.seal();
+        true
+    }
+
+    /// Redoes the last undone step, returning whether there was one.
+    ///
+    /// Commits any transaction in progress first.
+    #[inline]
+    pub fn redo(&mut self, graph: &mut AudioGraph) -> bool {
+        self.commit_transaction();
+
+        let Some(ops) = self.redo.pop() else {
+            return false;
+        };
+
+        for op in &ops {
+            op.apply(graph);
+        }
+
+        self.undo.push_back(ops);
+        self.seal();
+        true
+    }
+}
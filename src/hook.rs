@@ -0,0 +1,91 @@
+use super::*;
+
+/// Observes, and can skip, the tasks of a schedule, as [`run_schedule_with`] runs
+/// them with runners of type `R`.
+///
+/// Gating ([`ConditionalSchedule`]), tapping ([`TappedSchedule`]), tracing
+/// ([`BlockTracer`]) and deadline monitoring ([`DeadlineMonitor`]) are all hooks, and
+/// can be combined, as tuples of hooks are hooks too. `(A, B)` calls `A` first, and
+/// skips tasks that either of them skips. Hooks built for a schedule (e. g. by
+/// [`ConditionalSchedule::hook`]) must only be run with a schedule equal to it.
+pub trait TaskHook<R: ?Sized> {
+    /// Called before task `index` (of `task`) runs, returns whether it should run.
+    ///
+    /// Hooks skipping tasks must write to their outputs themselves, if needed.
+    #[inline]
+    fn before(&mut self, index: usize, task: &Task, runner: &mut R) -> bool {
+        let _ = (index, task, runner);
+        true
+    }
+
+    /// Called after task `index` (of `task`) has run, or was skipped, as reported by `ran`.
+    #[inline]
+    fn after(&mut self, index: usize, task: &Task, ran: bool, runner: &mut R) {
+        let _ = (index, task, ran, runner);
+    }
+}
+
+impl<R: ?Sized> TaskHook<R> for () {}
+
+impl<R: ?Sized, H: TaskHook<R> + ?Sized> TaskHook<R> for &mut H {
+    #[inline]
+    fn before(&mut self, index: usize, task: &Task, runner: &mut R) -> bool {
+        (**self).before(index, task, runner)
+    }
+
+    #[inline]
+    fn after(&mut self, index: usize, task: &Task, ran: bool, runner: &mut R) {
+        (**self).after(index, task, ran, runner);
+    }
+}
+
+// absent hooks, e. g. tracing when no trace was requested, do nothing
+impl<R: ?Sized, H: TaskHook<R>> TaskHook<R> for Option<H> {
+    #[inline]
+    fn before(&mut self, index: usize, task: &Task, runner: &mut R) -> bool {
+        self.as_mut()
+            .is_none_or(|hook| hook.before(index, task, runner))
+    }
+
+    #[inline]
+    fn after(&mut self, index: usize, task: &Task, ran: bool, runner: &mut R) {
+        if let Some(hook) = self {
+            hook.after(index, task, ran, runner);
+        }
+    }
+}
+
+impl<R: ?Sized, A: TaskHook<R>, B: TaskHook<R>> TaskHook<R> for (A, B) {
+    #[inline]
+    fn before(&mut self, index: usize, task: &Task, runner: &mut R) -> bool {
+        // both hooks see every task, even if the first one skips it
+        let a = self.0.before(index, task, runner);
+        let b = self.1.before(index, task, runner);
+        a && b
+    }
+
+    #[inline]
+    fn after(&mut self, index: usize, task: &Task, ran: bool, runner: &mut R) {
+        self.0.after(index, task, ran, runner);
+        self.1.after(index, task, ran, runner);
+    }
+}
+
+/// Like [`run_schedule`], but lets `hook` observe, and skip, every task. Doesn't
+/// allocate, unless `hook` does.
+#[inline]
+pub fn run_schedule_with<R: TaskRunner + ?Sized>(
+    schedule: &[Task],
+    runner: &mut R,
+    hook: &mut (impl TaskHook<R> + ?Sized),
+) {
+    for (index, task) in schedule.iter().enumerate() {
+        let run = hook.before(index, task, runner);
+
+        if run {
+            task.run(runner);
+        }
+
+        hook.after(index, task, run, runner);
+    }
+}
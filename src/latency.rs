@@ -0,0 +1,71 @@
+use super::*;
+
+/// A latency, in samples, or in units that can only be converted to samples once the
+/// sample rate (and, for musical units, the tempo) is known.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Latency {
+    Samples(u64),
+    Seconds(f64),
+    /// Quarter notes
+    Beats(f64),
+}
+
+impl From<u64> for Latency {
+    #[inline]
+    fn from(samples: u64) -> Self {
+        Self::Samples(samples)
+    }
+}
+
+impl Latency {
+    /// Converts this latency to samples, rounding to the nearest one, with `tempo`
+    /// in beats per minute. Negative latencies resolve to `0`.
+    #[inline]
+    pub fn to_samples(self, sample_rate: f64, tempo: f64) -> u64 {
+        let seconds = match self {
+            Self::Samples(samples) => return samples,
+            Self::Seconds(seconds) => seconds,
+            Self::Beats(beats) => beats * 60. / tempo,
+        };
+
+        // float to int casts saturate
+        (seconds * sample_rate).round() as u64
+    }
+}
+
+impl Node {
+    /// Sets a latency to be converted to samples, and stored in `self.latency`,
+    /// by [`AudioGraph::resolve_latencies`].
+    #[inline]
+    pub fn set_unresolved_latency(&mut self, latency: impl Into<Latency>) {
+        self.unresolved_latency = Some(latency.into());
+    }
+
+    #[inline]
+    pub fn unresolved_latency(&self) -> Option<Latency> {
+        self.unresolved_latency
+    }
+
+    #[inline]
+    pub fn clear_unresolved_latency(&mut self) -> Option<Latency> {
+        self.unresolved_latency.take()
+    }
+}
+
+impl AudioGraph {
+    /// Converts the unresolved latencies of all nodes to samples, updating their
+    /// latencies, given the sample rate and tempo (in beats per minute).
+    ///
+    /// Must be called again whenever either of them changes.
+    #[inline]
+    pub fn resolve_latencies(&mut self, sample_rate: f64, tempo: f64) {
+        let resolved = Vec::from_iter(self.nodes().filter_map(|(id, node)| {
+            node.unresolved_latency()
+                .map(|latency| (id.clone(), latency.to_samples(sample_rate, tempo)))
+        }));
+
+        for (id, latency) in resolved {
+            self.set_latency(&id, latency);
+        }
+    }
+}
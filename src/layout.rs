@@ -0,0 +1,261 @@
+use super::*;
+
+/// Where a node, or an edge's waypoint, is placed in a [`GraphLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LayoutPosition {
+    /// Signal flows from lower to higher layers
+    pub layer: usize,
+    /// Index within the layer
+    pub index: usize,
+}
+
+/// An entry of a [`GraphLayout`] layer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LayoutSlot {
+    Node(NodeID),
+    /// A waypoint of an edge spanning more than one layer
+    Edge {
+        from: NodeID,
+        to: NodeID,
+    },
+}
+
+/// Layered (Sugiyama-style) layout of a graph, for patch editors to auto-arrange nodes
+/// consistently with signal flow. See [`AudioGraph::layout`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphLayout {
+    layers: Vec<Vec<LayoutSlot>>,
+    positions: FnvHashMap<NodeID, LayoutPosition>,
+    routes: FnvHashMap<(NodeID, NodeID), Vec<LayoutPosition>>,
+}
+
+impl GraphLayout {
+    #[inline]
+    pub fn layers(&self) -> &[Vec<LayoutSlot>] {
+        &self.layers
+    }
+
+    #[inline]
+    pub fn position(&self, node: &NodeID) -> Option<LayoutPosition> {
+        self.positions.get(node).copied()
+    }
+
+    /// Returns the waypoints the edges from `from` to `to` should be routed through,
+    /// one per layer between them. Empty if the nodes are in consecutive layers, or
+    /// not connected.
+    #[inline]
+    pub fn route(&self, from: &NodeID, to: &NodeID) -> &[LayoutPosition] {
+        self.routes
+            .get(&(from.clone(), to.clone()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the number of segments crossing each other between consecutive layers.
+    #[inline]
+    pub fn crossings(&self) -> usize {
+        let segments = self.segments();
+
+        segments
+            .iter()
+            .enumerate()
+            .map(|(i, &(a, b))| {
+                segments[i + 1..]
+                    .iter()
+                    .filter(|&&(c, d)| {
+                        a.layer == c.layer
+                            && (a.index < c.index && b.index > d.index
+                                || a.index > c.index && b.index < d.index)
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
+    // pairs of positions in consecutive layers, linked by (part of) an edge
+    fn segments(&self) -> Vec<(LayoutPosition, LayoutPosition)> {
+        let mut segments = vec![];
+
+        for ((from, to), route) in &self.routes {
+            let mut previous = self.positions[from];
+
+            for &position in route.iter().chain([&self.positions[to]]) {
+                segments.push((previous, position));
+                previous = position;
+            }
+        }
+
+        segments
+    }
+}
+
+impl AudioGraph {
+    /// Computes a layered layout of the graph: every node is placed in the layer after
+    /// the last one of it's sources, and nodes are ordered within layers to reduce
+    /// crossing edges.
+    #[inline]
+    pub fn layout(&self) -> GraphLayout {
+        fn layer_of(
+            graph: &AudioGraph,
+            node: &NodeID,
+            layers: &mut FnvHashMap<NodeID, usize>,
+        ) -> usize {
+            if let Some(&layer) = layers.get(node) {
+                return layer;
+            }
+
+            let layer = graph
+                .sources(node)
+                .map(|source| layer_of(graph, &source, layers) + 1)
+                .max()
+                .unwrap_or(0);
+
+            layers.insert(node.clone(), layer);
+            layer
+        }
+
+        let mut ids = Vec::from_iter(self.nodes().map(|(id, _)| id.clone()));
+        ids.sort_unstable();
+
+        let mut node_layers = FnvHashMap::default();
+        for id in &ids {
+            layer_of(self, id, &mut node_layers);
+        }
+
+        let num_layers = node_layers.values().max().map_or(0, |&max| max + 1);
+
+        let mut edges = Vec::from_iter(ids.iter().flat_map(|to| {
+            let mut sources = Vec::from_iter(self.sources(to));
+            sources.sort_unstable();
+            sources.into_iter().map(move |from| (from, to.clone()))
+        }));
+        edges.dedup();
+
+        // every slot (node or waypoint), and it's neighbors in the previous layer
+        let mut slots = Vec::<(LayoutSlot, usize)>::new();
+        let mut upper = Vec::<Vec<usize>>::new();
+        let mut slot_of = FnvHashMap::default();
+
+        for id in &ids {
+            slot_of.insert(id.clone(), slots.len());
+            slots.push((LayoutSlot::Node(id.clone()), node_layers[id]));
+            upper.push(vec![]);
+        }
+
+        for (from, to) in &edges {
+            let mut previous = slot_of[from];
+
+            for layer in node_layers[from] + 1..node_layers[to] {
+                slots.push((
+                    LayoutSlot::Edge {
+                        from: from.clone(),
+                        to: to.clone(),
+                    },
+                    layer,
+                ));
+                upper.push(vec![previous]);
+                previous = slots.len() - 1;
+            }
+
+            upper[slot_of[to]].push(previous);
+        }
+
+        let mut lower = vec![vec![]; slots.len()];
+        for (slot, neighbors) in upper.iter().enumerate() {
+            for &neighbor in neighbors {
+                lower[neighbor].push(slot);
+            }
+        }
+
+        let mut layers = vec![vec![]; num_layers];
+        for (slot, &(_, layer)) in slots.iter().enumerate() {
+            layers[layer].push(slot);
+        }
+
+        let mut index = vec![0; slots.len()];
+        let update_indices = |layer: &[usize], index: &mut [usize]| {
+            for (i, &slot) in layer.iter().enumerate() {
+                index[slot] = i;
+            }
+        };
+
+        for layer in &layers {
+            update_indices(layer, &mut index);
+        }
+
+        // barycenter heuristic, alternating downward and upward sweeps
+        for sweep in 0..8 {
+            let downward = sweep % 2 == 0;
+            let (neighbors, order) = if downward {
+                (&upper, Vec::from_iter(1..num_layers))
+            } else {
+                (
+                    &lower,
+                    Vec::from_iter((0..num_layers.saturating_sub(1)).rev()),
+                )
+            };
+
+            for layer in order {
+                let barycenter = |slot: usize| {
+                    let neighbors = &neighbors[slot];
+
+                    if neighbors.is_empty() {
+                        index[slot] as f64
+                    } else {
+                        neighbors.iter().map(|&n| index[n] as f64).sum::<f64>()
+                            / neighbors.len() as f64
+                    }
+                };
+
+                let mut keyed = Vec::from_iter(layers[layer].iter().map(|&s| (barycenter(s), s)));
+                // stable, so that ties keep their current order
+                keyed.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+                layers[layer] = Vec::from_iter(keyed.into_iter().map(|(_, slot)| slot));
+                update_indices(&layers[layer], &mut index);
+            }
+        }
+
+        let position = |slot: usize| LayoutPosition {
+            layer: slots[slot].1,
+            index: index[slot],
+        };
+
+        let mut routes = FnvHashMap::<_, Vec<_>>::default();
+        for (slot, (kind, _)) in slots.iter().enumerate() {
+            if let LayoutSlot::Edge { from, to } = kind {
+                routes
+                    .entry((from.clone(), to.clone()))
+                    .or_default()
+                    .push(position(slot));
+            }
+        }
+
+        for (from, to) in edges {
+            routes.entry((from, to)).or_default();
+        }
+
+        GraphLayout {
+            positions: ids
+                .iter()
+                .map(|id| (id.clone(), position(slot_of[id])))
+                .collect(),
+            layers: layers
+                .into_iter()
+                .map(|layer| {
+                    layer
+                        .into_iter()
+                        .map(|slot| slots[slot].0.clone())
+                        .collect()
+                })
+                .collect(),
+            routes,
+        }
+    }
+
+    fn sources<'a>(&'a self, node: &NodeID) -> impl Iterator<Item = NodeID> + 'a {
+        self[node]
+            .inputs()
+            .values()
+            .flat_map(|input| input.connections().keys().cloned())
+    }
+}
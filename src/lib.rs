@@ -1,8 +1,13 @@
-use core::{hash::Hash, iter, mem, ops::Index};
+use core::{
+    any::Any,
+    hash::Hash,
+    iter, mem,
+    ops::{Index, Range},
+};
 use fnv::{FnvHashMap, FnvHashSet};
-use std::collections::hash_map::Entry;
+use std::{collections::hash_map::Entry, sync::Arc};
 
-#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct InputID(u32);
 
 impl InputID {
@@ -12,7 +17,7 @@ impl InputID {
     }
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct OutputID(u32);
 
 impl OutputID {
@@ -22,9 +27,70 @@ impl OutputID {
     }
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct NodeID(u32);
 
+impl NodeID {
+    /// Derives a seed for this node's random number generators (e. g. noise sources) from
+    /// the host's `master_seed`. Always the same for the same node ID and master seed, so
+    /// that renders are reproducible, but uncorrelated between nodes.
+    #[inline]
+    pub fn seed(&self, master_seed: u64) -> u64 {
+        // SplitMix64's finalizer
+        let mut z = master_seed ^ u64::from(self.0).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(any(test, feature = "alloc-guard"))]
+pub mod alloc_guard;
+mod analysis;
+mod cache;
+mod changes;
+mod compact;
+mod conditional;
+mod cost;
+mod deadline;
+mod editor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+mod history;
+mod hook;
+mod latency;
+mod layout;
+mod ordering;
+mod schedule;
+mod service;
+mod snapshot;
+mod tap;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod topological;
+mod trace;
+pub use analysis::*;
+pub use cache::*;
+pub use changes::*;
+pub use compact::*;
+pub use conditional::*;
+pub use cost::*;
+pub use deadline::*;
+pub use editor::*;
+pub use history::*;
+pub use hook::*;
+pub use latency::*;
+pub use layout::*;
+pub use ordering::*;
+pub use schedule::*;
+pub use service::*;
+pub use snapshot::*;
+pub use tap::*;
+use topological::*;
+pub use trace::*;
+
 #[cfg(test)]
 mod tests;
 
@@ -48,6 +114,18 @@ impl Input {
         }
     }
 
+    /// Reserves space for connections from at least `additional` more nodes.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+        self.0.values_mut().for_each(FnvHashSet::shrink_to_fit);
+    }
+
     #[inline]
     pub fn remove_port(&mut self, (node_index, port_index): (&NodeID, &OutputID)) -> bool {
         let mut empty = false;
@@ -69,8 +147,13 @@ impl Input {
 #[derive(Clone, Debug, Default)]
 pub struct Node {
     pub latency: u64,
+    unresolved_latency: Option<Latency>,
     output_ids: FnvHashSet<OutputID>,
     inputs: FnvHashMap<InputID, Input>,
+    input_defaults: FnvHashMap<InputID, f32>,
+    max_block_size: Option<usize>,
+    tail: u64,
+    user_data: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 impl Node {
@@ -79,9 +162,15 @@ impl Node {
             latency,
             output_ids,
             inputs,
+            ..
         } = self;
         Self {
             latency: *latency,
+            unresolved_latency: None,
+            input_defaults: FnvHashMap::default(),
+            max_block_size: None,
+            tail: 0,
+            user_data: None,
             output_ids: inputs.keys().cloned().map(InputID::transpose).collect(),
             inputs: output_ids
                 .iter()
@@ -114,9 +203,32 @@ impl Node {
 
     #[inline]
     pub fn remove_input(&mut self, id: &InputID) -> Option<Input> {
+        self.input_defaults.remove(id);
         self.inputs.remove(id)
     }
 
+    /// Sets the constant value an input port should be fed when it isn't connected.
+    /// Unconnected input ports are absent from the node's task in compiled schedules.
+    ///
+    /// Returns the previous default value, or `None` if the input doesn't exist.
+    #[inline]
+    pub fn set_input_default(&mut self, id: &InputID, value: f32) -> Option<f32> {
+        if !self.inputs.contains_key(id) {
+            return None;
+        }
+
+        Some(self.input_defaults.insert(id.clone(), value).unwrap_or(0.))
+    }
+
+    /// Returns the constant value an input should be fed when it isn't connected
+    /// (`0.` unless set otherwise), or `None` if the input doesn't exist.
+    #[inline]
+    pub fn input_default(&self, id: &InputID) -> Option<f32> {
+        self.inputs
+            .contains_key(id)
+            .then(|| self.input_defaults.get(id).copied().unwrap_or(0.))
+    }
+
     #[inline]
     pub fn add_output(&mut self) -> OutputID {
         #[allow(clippy::useless_conversion)]
@@ -131,6 +243,89 @@ impl Node {
 
         panic!("Index overflow")
     }
+
+    #[inline]
+    pub fn reserve_ports(&mut self, additional_inputs: usize, additional_outputs: usize) {
+        self.inputs.reserve(additional_inputs);
+        self.output_ids.reserve(additional_outputs);
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.inputs.shrink_to_fit();
+        self.output_ids.shrink_to_fit();
+        self.inputs.values_mut().for_each(Input::shrink_to_fit);
+    }
+
+    #[inline]
+    pub fn add_input_bus(&mut self, width: usize) -> Vec<InputID> {
+        iter::repeat_with(|| self.add_input()).take(width).collect()
+    }
+
+    #[inline]
+    pub fn add_output_bus(&mut self, width: usize) -> Vec<OutputID> {
+        iter::repeat_with(|| self.add_output())
+            .take(width)
+            .collect()
+    }
+}
+
+/// User data (UI metadata, type information...) can be attached to nodes. It is never
+/// read by the scheduler, and cloning a node shares it's user data with the clone.
+impl Node {
+    #[inline]
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        self.user_data.as_deref()?.downcast_ref()
+    }
+
+    #[inline]
+    pub fn set_user_data(
+        &mut self,
+        data: impl Any + Send + Sync,
+    ) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.user_data.replace(Arc::new(data))
+    }
+
+    #[inline]
+    pub fn take_user_data(&mut self) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.user_data.take()
+    }
+}
+
+/// Some nodes (e. g. ones with a fixed FFT hop size) can only process a limited number
+/// of samples per call. Runners should split such nodes' calls using [`split_block`],
+/// while other nodes still process whole blocks.
+impl Node {
+    #[inline]
+    pub fn max_block_size(&self) -> Option<usize> {
+        self.max_block_size
+    }
+
+    /// # Panics
+    ///
+    /// if `max_block_size` is `Some(0)`
+    #[inline]
+    pub fn set_max_block_size(&mut self, max_block_size: Option<usize>) -> Option<usize> {
+        assert_ne!(max_block_size, Some(0), "block sizes must be positive");
+        mem::replace(&mut self.max_block_size, max_block_size)
+    }
+}
+
+/// Nodes like reverbs and delays keep outputting sound after their input stops. Their tail
+/// is the number of samples it takes for their output to decay to silence, see
+/// [`AudioGraph::tail_length`].
+impl Node {
+    #[inline]
+    pub fn tail(&self) -> u64 {
+        self.tail
+    }
+
+    /// `u64::MAX` means the node's output may never decay (e. g. oscillators, or
+    /// feedback delays with a gain of 1).
+    #[inline]
+    pub fn set_tail(&mut self, tail: u64) -> u64 {
+        mem::replace(&mut self.tail, tail)
+    }
 }
 
 impl Node {
@@ -156,19 +351,18 @@ impl BufferAllocator {
         self.ports.len()
     }
 
-    fn get_free(&mut self) -> usize {
-        fn get_or_insert_empty_set_index<T>(list: &mut Vec<FnvHashSet<T>>) -> usize {
-            list.iter()
-                .enumerate()
-                .find_map(|(i, port_idxs)| port_idxs.is_empty().then_some(i))
-                .unwrap_or_else(|| {
-                    let tmp = list.len();
-                    list.push(FnvHashSet::default());
-                    tmp
-                })
-        }
-
-        get_or_insert_empty_set_index(&mut self.ports)
+    fn get_free(&mut self, excluded: &FnvHashSet<usize>) -> usize {
+        self.ports
+            .iter()
+            .enumerate()
+            .find_map(|(i, port_idxs)| {
+                (port_idxs.is_empty() && !excluded.contains(&i)).then_some(i)
+            })
+            .unwrap_or_else(|| {
+                let tmp = self.ports.len();
+                self.ports.push(FnvHashSet::default());
+                tmp
+            })
     }
 
     fn claim(
@@ -198,8 +392,9 @@ impl BufferAllocator {
         new
     }
 
-    fn remove_claim(&mut self, port: &(NodeID, InputID)) -> usize {
-        let i = self.buffers.remove(port).unwrap();
+    /// Returns `None` if `port` hasn't claimed any buffer (i. e. it is unconnected)
+    fn remove_claim(&mut self, port: &(NodeID, InputID)) -> Option<usize> {
+        let i = self.buffers.remove(port)?;
 
         assert!(
             self.ports
@@ -209,7 +404,7 @@ impl BufferAllocator {
             "INTERNAL ERROR: port reserves a buffer but is not in it's port list entry"
         );
 
-        i
+        Some(i)
     }
 }
 
@@ -249,6 +444,76 @@ impl Task {
             output,
         }
     }
+
+    #[inline]
+    pub fn run(&self, runner: &mut (impl TaskRunner + ?Sized)) {
+        match self {
+            Self::Node {
+                id,
+                inputs,
+                outputs,
+            } => runner.run_node(id, inputs, outputs),
+            Self::Sum {
+                left,
+                right,
+                output,
+            } => runner.run_sum(*left, *right, *output),
+        }
+    }
+}
+
+/// Executes the individual tasks of a compiled schedule.
+///
+/// Implementors own the buffers (the `usize`s in each task are indices into them),
+/// and [`run_schedule`] takes care of walking the schedule in the correct order.
+pub trait TaskRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    );
+
+    /// Sum the contents of buffers `left` and `right` into buffer `output`,
+    /// which may alias either of them, unless the schedule was compiled with
+    /// [`SumBufferPolicy::AlwaysNew`].
+    fn run_sum(&mut self, left: usize, right: usize, output: usize);
+}
+
+/// Whether sum tasks may write their result into one of the buffers they read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SumBufferPolicy {
+    /// Write into any buffer not used anymore, including the summed ones, uses
+    /// fewer buffers.
+    #[default]
+    ReuseWhenSafe,
+    /// Always write into a buffer other than the summed ones, for runners that
+    /// can't sum in place.
+    AlwaysNew,
+}
+
+#[inline]
+pub fn run_schedule<'a>(
+    schedule: impl IntoIterator<Item = &'a Task>,
+    runner: &mut (impl TaskRunner + ?Sized),
+) {
+    for task in schedule {
+        task.run(runner);
+    }
+}
+
+/// Returns the consecutive sample ranges a block of `block_size` samples must be split
+/// into, so that none of them is longer than `max_block_size`, if any.
+#[inline]
+pub fn split_block(
+    block_size: usize,
+    max_block_size: Option<usize>,
+) -> impl Iterator<Item = Range<usize>> {
+    let step = max_block_size.unwrap_or(block_size).max(1);
+
+    (0..block_size)
+        .step_by(step)
+        .map(move |start| start..block_size.min(start + step))
 }
 
 #[derive(Debug)]
@@ -258,34 +523,78 @@ struct Scheduler {
 }
 
 impl Scheduler {
-    fn compile(self) -> (usize, Vec<Task>) {
+    fn compile_into(self, schedule: &mut Vec<Task>, sum_buffers: SumBufferPolicy) -> usize {
         let mut allocator = BufferAllocator::default();
-        let mut schedule = vec![];
 
         let Self {
             mut transposed,
             process_order,
         } = self;
 
+        // the port maps of the previous schedule's node tasks are cleared, and reused
+        let (mut input_maps, mut output_maps): (Vec<_>, Vec<_>) = schedule
+            .drain(..)
+            .filter_map(|task| match task {
+                Task::Node {
+                    mut inputs,
+                    mut outputs,
+                    ..
+                } => {
+                    inputs.clear();
+                    outputs.clear();
+                    Some((inputs, outputs))
+                }
+                Task::Sum { .. } => None,
+            })
+            .unzip();
+
+        // at least one task per node, sum tasks may still cause a reallocation
+        schedule.reserve(process_order.len());
+
         for node_id in process_order {
             let node = transposed.get_node_mut(&node_id).unwrap();
 
-            #[allow(clippy::clone_on_copy)]
-            let inputs = node
-                .output_ids()
-                .iter()
-                .cloned()
-                .map(OutputID::transpose)
-                .map(|id| (id.clone(), allocator.remove_claim(&(node_id.clone(), id))))
-                .collect();
-
-            let outputs = node
+            // unconnected inputs don't claim any buffer, and are left out
+            let mut inputs = input_maps.pop().unwrap_or_default();
+            inputs.extend(
+                node.output_ids()
+                    .iter()
+                    .cloned()
+                    .map(OutputID::transpose)
+                    .filter_map(|id| {
+                        let buf = allocator.remove_claim(&(node_id.clone(), id.clone()))?;
+                        Some((id, buf))
+                    }),
+            );
+
+            let mut outputs = output_maps.pop().unwrap_or_default();
+            let mut redundant_claims = Vec::new();
+
+            for (id, port) in node
                 .inputs()
                 .iter()
                 .filter(|(_, port)| !port.connections().is_empty())
-                .map(|(id, _)| id.clone().transpose())
-                .zip(iter::repeat_with(|| allocator.get_free()))
-                .collect();
+            {
+                // an output buffer whose claims are all redundant looks free,
+                // so it has to be excluded explicitly
+                let buf_index = allocator.get_free(&outputs.values().copied().collect());
+                outputs.insert(id.clone().transpose(), buf_index);
+
+                redundant_claims.extend(
+                    allocator
+                        .claim(
+                            buf_index,
+                            port.connections()
+                                .iter()
+                                .flat_map(|(node, ports)| {
+                                    ports.iter().map(|p| (node.clone(), p.clone().transpose()))
+                                })
+                                .collect(),
+                        )
+                        .into_iter()
+                        .map(|port_idx| (buf_index, port_idx)),
+                );
+            }
 
             schedule.push(Task::Node {
                 id: node_id,
@@ -293,45 +602,49 @@ impl Scheduler {
                 outputs,
             });
 
-            let Some(Task::Node { outputs, .. }) = schedule.last() else {
-                panic!()
-            };
-
-            for (buf_index, port) in outputs.clone().into_values().zip(node.inputs.values()) {
-                for port_idx in allocator.claim(
-                    buf_index,
-                    port.connections()
-                        .iter()
-                        .flat_map(|(node, ports)| {
-                            ports.iter().map(|p| (node.clone(), p.clone().transpose()))
-                        })
-                        .collect(),
-                ) {
-                    let other_buf_idx = allocator.remove_claim(&port_idx);
-                    let new_free_buf = allocator.get_free();
-                    assert!(
-                        allocator
-                            .claim(new_free_buf, FnvHashSet::from_iter([port_idx]))
-                            .is_empty(),
-                        "INTERNAL ERROR: redundant claims cleared yet still found"
-                    );
-
-                    schedule.push(Task::Sum {
-                        left: buf_index,
-                        right: other_buf_idx,
-                        output: new_free_buf,
-                    });
+            for i in 0..redundant_claims.len() {
+                let (buf_index, port_idx) = redundant_claims[i].clone();
+
+                let other_buf_idx = allocator
+                    .remove_claim(&port_idx)
+                    .expect("INTERNAL ERROR: redundant claim found for unclaimed port");
+
+                // output buffers still read by the remaining sums must not be overwritten
+                let mut excluded =
+                    FnvHashSet::from_iter(redundant_claims[i + 1..].iter().map(|&(buf, _)| buf));
+
+                if sum_buffers == SumBufferPolicy::AlwaysNew {
+                    excluded.extend([buf_index, other_buf_idx]);
                 }
+
+                let new_free_buf = allocator.get_free(&excluded);
+
+                assert!(
+                    allocator
+                        .claim(new_free_buf, FnvHashSet::from_iter([port_idx]))
+                        .is_empty(),
+                    "INTERNAL ERROR: redundant claims cleared yet still found"
+                );
+
+                schedule.push(Task::Sum {
+                    left: buf_index,
+                    right: other_buf_idx,
+                    output: new_free_buf,
+                });
             }
         }
 
-        (allocator.len(), schedule)
+        allocator.len()
     }
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct AudioGraph {
-    nodes: FnvHashMap<NodeID, Node>,
+    // Nodes are shared between a graph and it's snapshots, and cloned on write
+    nodes: FnvHashMap<NodeID, Arc<Node>>,
+    // shared with snapshots too
+    order: Arc<TopologicalOrder>,
+    changes: Option<Vec<GraphChange>>,
 }
 
 impl Index<&NodeID> for AudioGraph {
@@ -343,6 +656,54 @@ impl Index<&NodeID> for AudioGraph {
 }
 
 impl AudioGraph {
+    #[inline]
+    pub fn with_capacity(nodes: usize) -> Self {
+        Self {
+            nodes: FnvHashMap::with_capacity_and_hasher(nodes, Default::default()),
+            order: Arc::default(),
+            changes: None,
+        }
+    }
+
+    #[inline]
+    pub fn reserve_nodes(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    /// Reserves room for `additional` more source nodes connected to the input `input`
+    /// of `node`. Returns `false` if the port doesn't exist.
+    #[inline]
+    pub fn reserve_edges(&mut self, (node, input): (&NodeID, &InputID), additional: usize) -> bool {
+        // checked first, so that shared nodes aren't copied for nothing
+        if !self
+            .get_node(node)
+            .is_some_and(|node| node.inputs().contains_key(input))
+        {
+            return false;
+        }
+
+        self.get_node_mut(node)
+            .unwrap()
+            .get_input_mut(input)
+            .unwrap()
+            .reserve(additional);
+
+        true
+    }
+
+    /// Shrinks the storage of the graph, and that of all of it's nodes, as much as possible.
+    ///
+    /// Nodes shared with snapshots, or clones, of the graph are left as they are, instead
+    /// of being copied.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.nodes
+            .values_mut()
+            .filter_map(Arc::get_mut)
+            .for_each(Node::shrink_to_fit);
+    }
+
     #[inline]
     fn fill_inputs(&mut self, transposed: &Self, node_index: &NodeID, processed: &mut Vec<NodeID>) {
         if processed.contains(node_index) {
@@ -374,6 +735,7 @@ impl AudioGraph {
                         node
                     };
 
+                    // bypasses the maintained topological order, unused by transposed graphs
                     let new = node
                         .get_input_mut(&input_id)
                         .unwrap()
@@ -389,17 +751,20 @@ impl AudioGraph {
 
     #[inline]
     fn scheduler(&self, root_nodes: FnvHashSet<NodeID>) -> Scheduler {
-        let mut transposed = Self::default();
+        let mut transposed = Self::with_capacity(self.nodes.len());
 
-        let mut process_order = vec![];
+        let mut process_order = Vec::with_capacity(self.nodes.len());
 
         for node_idx in root_nodes {
-            assert!(transposed
-                .try_insert_node(
-                    node_idx.clone(),
-                    self.get_node(&node_idx).unwrap().with_reversed_io_layout()
-                )
-                .is_ok());
+            // root nodes may have already been inserted, if other root nodes depend on them
+            if transposed.get_node(&node_idx).is_none() {
+                assert!(transposed
+                    .try_insert_node(
+                        node_idx.clone(),
+                        self.get_node(&node_idx).unwrap().with_reversed_io_layout()
+                    )
+                    .is_ok());
+            }
             transposed.fill_inputs(self, &node_idx, &mut process_order);
         }
 
@@ -411,7 +776,35 @@ impl AudioGraph {
 
     #[inline]
     pub fn compile(&self, root_nodes: impl IntoIterator<Item = NodeID>) -> (usize, Vec<Task>) {
-        self.scheduler(FnvHashSet::from_iter(root_nodes)).compile()
+        let mut schedule = vec![];
+        let num_buffers = self.compile_into(root_nodes, &mut schedule);
+        (num_buffers, schedule)
+    }
+
+    /// Like [`Self::compile`], but writes the schedule into `schedule`, replacing it's
+    /// contents while reusing it's allocation, and only returns the number of buffers.
+    #[inline]
+    pub fn compile_into(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+        schedule: &mut Vec<Task>,
+    ) -> usize {
+        self.scheduler(FnvHashSet::from_iter(root_nodes))
+            .compile_into(schedule, SumBufferPolicy::default())
+    }
+
+    /// Like [`Self::compile`], but, where dependencies allow it, schedules nodes for
+    /// which `key` returns equal values (e. g. nodes of the same type) consecutively.
+    #[inline]
+    pub fn compile_grouped<K: Eq>(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+        key: impl FnMut(&NodeID, &Node) -> K,
+    ) -> (usize, Vec<Task>) {
+        self.compile_with(
+            root_nodes,
+            CompileOptions::new().ordering(GroupBy::new(key)),
+        )
     }
 }
 
@@ -434,52 +827,285 @@ impl AudioGraph {
             return Err(false);
         }
 
-        if self.is_connected(&from.0, &to.0) {
+        if self.would_cycle(&from.0, &to.0) {
             return Err(true);
         }
 
-        Ok(self
+        let new = self
             .get_node_mut(&to.0)
             .unwrap()
             .get_input_mut(&to.1)
             .unwrap()
-            .insert_output(from))
+            .insert_output(from.clone());
+
+        if new {
+            self.record_change(|| GraphChange::EdgeInserted { from, to });
+        }
+
+        Ok(new)
+    }
+
+    /// Connects every output port in `from` to the input port at the same position in `to`,
+    /// either inserting all edges, or none of them.
+    ///
+    /// Returns `Err(true)` if this would create a cycle, `Err(false)` if any of the ports
+    /// don't exist or the buses have different widths, and `Ok(true)` if at least one
+    /// of the edges is new.
+    #[inline]
+    pub fn try_insert_bus_edge(
+        &mut self,
+        (from, outputs): (NodeID, &[OutputID]),
+        (to, inputs): (NodeID, &[InputID]),
+    ) -> Result<bool, bool> {
+        let (Some(from_node), Some(to_node)) = (self.get_node(&from), self.get_node(&to)) else {
+            return Err(false);
+        };
+
+        if outputs.len() != inputs.len()
+            || !outputs.iter().all(|id| from_node.output_ids().contains(id))
+            || !inputs.iter().all(|id| to_node.inputs().contains_key(id))
+        {
+            return Err(false);
+        }
+
+        if self.would_cycle(&from, &to) {
+            return Err(true);
+        }
+
+        let mut any_new = false;
+
+        for (output, input) in iter::zip(outputs, inputs) {
+            let (from, to) = ((from.clone(), output.clone()), (to.clone(), input.clone()));
+
+            let new = self
+                .get_node_mut(&to.0)
+                .unwrap()
+                .get_input_mut(&to.1)
+                .unwrap()
+                .insert_output(from.clone());
+
+            if new {
+                self.record_change(|| GraphChange::EdgeInserted { from, to });
+            }
+
+            any_new |= new;
+        }
+
+        Ok(any_new)
     }
 
+    /// Inserts all `edges`, or none of them. Cycles are detected against the maintained
+    /// topological order, so only the nodes between the ends of edges going against it
+    /// are visited.
+    ///
+    /// Returns `Err(true)` if this would create a cycle, `Err(false)` if any of the ports
+    /// don't exist, and the number of new edges otherwise.
+    #[inline]
+    pub fn try_insert_edges(
+        &mut self,
+        edges: impl IntoIterator<Item = Edge>,
+    ) -> Result<usize, bool> {
+        let edges = Vec::from_iter(edges);
+
+        if !edges.iter().all(|((from, output), (to, input))| {
+            self.get_node(from)
+                .is_some_and(|node| node.output_ids().contains(output))
+                && self
+                    .get_node(to)
+                    .is_some_and(|node| node.inputs().contains_key(input))
+        }) {
+            return Err(false);
+        }
+
+        // restored, along with the edges inserted so far, if any edge creates a cycle
+        let order = self.order.clone();
+        let mut new_edges = Vec::<Edge>::new();
+
+        for (from, to) in edges {
+            if self.would_cycle(&from.0, &to.0) {
+                for (from, to) in new_edges {
+                    self.get_node_mut(&to.0)
+                        .unwrap()
+                        .get_input_mut(&to.1)
+                        .unwrap()
+                        .remove_port((&from.0, &from.1));
+                }
+
+                self.order = order;
+                return Err(true);
+            }
+
+            let new = self
+                .get_node_mut(&to.0)
+                .unwrap()
+                .get_input_mut(&to.1)
+                .unwrap()
+                .insert_output(from.clone());
+
+            if new {
+                new_edges.push((from, to));
+            }
+        }
+
+        let num_new_edges = new_edges.len();
+
+        for (from, to) in new_edges {
+            self.record_change(|| GraphChange::EdgeInserted { from, to });
+        }
+
+        Ok(num_new_edges)
+    }
+
+    /// Returns whether the edge existed.
+    #[inline]
+    pub fn remove_edge(&mut self, from: (NodeID, OutputID), to: (NodeID, InputID)) -> bool {
+        let removed = self
+            .get_node_mut(&to.0)
+            .and_then(|node| node.get_input_mut(&to.1))
+            .is_some_and(|input| input.remove_port((&from.0, &from.1)));
+
+        if removed {
+            self.record_change(|| GraphChange::EdgeRemoved { from, to });
+        }
+
+        removed
+    }
+
+    /// Removes the node at `id`, along with all edges connected to it.
+    ///
+    /// The returned node keeps it's ports, but not it's connections.
+    #[inline]
+    pub fn remove_node(&mut self, id: &NodeID) -> Option<Node> {
+        let mut node = Arc::unwrap_or_clone(self.nodes.remove(id)?);
+        Arc::make_mut(&mut self.order).remove(id);
+
+        let mut removed_edges = vec![];
+
+        for (node_id, other) in &mut self.nodes {
+            if other
+                .inputs()
+                .values()
+                .all(|input| !input.connections().contains_key(id))
+            {
+                continue;
+            }
+
+            for (input_id, input) in &mut Arc::make_mut(other).inputs {
+                for output_id in input.0.remove(id).into_iter().flatten() {
+                    removed_edges
+                        .push(((id.clone(), output_id), (node_id.clone(), input_id.clone())));
+                }
+            }
+        }
+
+        for (input_id, input) in &mut node.inputs {
+            for (node_id, output_ids) in input.0.drain() {
+                for output_id in output_ids {
+                    removed_edges
+                        .push(((node_id.clone(), output_id), (id.clone(), input_id.clone())));
+                }
+            }
+        }
+
+        for (from, to) in removed_edges {
+            self.record_change(|| GraphChange::EdgeRemoved { from, to });
+        }
+
+        self.record_change(|| GraphChange::NodeRemoved(id.clone()));
+
+        Some(node)
+    }
+
+    /// Returns the node's previous latency, or `None` if no node exists at `id`.
+    #[inline]
+    pub fn set_latency(&mut self, id: &NodeID, latency: u64) -> Option<u64> {
+        let old = mem::replace(&mut self.get_node_mut(id)?.latency, latency);
+
+        if old != latency {
+            self.record_change(|| GraphChange::LatencyChanged {
+                node: id.clone(),
+                old,
+                new: latency,
+            });
+        }
+
+        Some(old)
+    }
+
+    /// Returns whether inserting an edge from `from` to `to` would create a cycle. If it
+    /// wouldn't, reorders nodes so that the maintained topological order stays valid with
+    /// the edge inserted.
+    ///
     /// # Panics
     ///
     /// if no node exists at either `from` or `to`
-    fn is_connected(&self, from: &NodeID, to: &NodeID) -> bool {
+    fn would_cycle(&mut self, from: &NodeID, to: &NodeID) -> bool {
         if from == to {
             return true;
         }
 
-        for port in self.get_node(from).unwrap().inputs().values() {
-            for node in port.connections().keys() {
-                if self.is_connected(node, to) {
+        let (lower, upper) = (self.order.rank(to), self.order.rank(from));
+
+        if upper < lower {
+            return false;
+        }
+
+        // nodes `from` depends on, ranked after `to`, only these can depend on `to`
+        let mut upstream = FnvHashSet::from_iter([from.clone()]);
+        let mut stack = vec![from];
+
+        while let Some(node) = stack.pop() {
+            for source in self[node]
+                .inputs()
+                .values()
+                .flat_map(|input| input.connections().keys())
+            {
+                if source == to {
                     return true;
                 }
+
+                if self.order.rank(source) > lower && upstream.insert(source.clone()) {
+                    stack.push(source);
+                }
             }
         }
 
+        Arc::make_mut(&mut self.order).move_before(lower..=upper, &upstream);
         false
     }
 
+    /// Returns the graph's nodes, each of them after all the nodes it depends on. This order
+    /// is maintained as the graph is edited, so no traversal of the graph happens here.
+    #[inline]
+    pub fn topological_order(&self) -> impl Iterator<Item = &NodeID> {
+        self.order.iter()
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> impl Iterator<Item = (&NodeID, &Node)> {
+        self.nodes.iter().map(|(id, node)| (id, node.as_ref()))
+    }
+
     #[inline]
     pub fn get_node(&self, index: &NodeID) -> Option<&Node> {
-        self.nodes.get(index)
+        self.nodes.get(index).map(Arc::as_ref)
     }
 
+    /// If this graph shares the node with a snapshot, it is cloned first.
     #[inline]
     pub fn get_node_mut(&mut self, index: &NodeID) -> Option<&mut Node> {
-        self.nodes.get_mut(index)
+        self.nodes.get_mut(index).map(Arc::make_mut)
     }
 
     #[inline]
+    #[allow(clippy::result_large_err)]
     fn try_insert_node(&mut self, id: NodeID, node: Node) -> Result<&mut Node, (&mut Node, Node)> {
         match self.nodes.entry(id) {
-            Entry::Occupied(e) => Err((e.into_mut(), node)),
-            Entry::Vacant(e) => Ok(e.insert(node)),
+            Entry::Occupied(e) => Err((Arc::make_mut(e.into_mut()), node)),
+            Entry::Vacant(e) => {
+                Arc::make_mut(&mut self.order).push(e.key().clone());
+                Ok(Arc::make_mut(e.insert(Arc::new(node))))
+            }
         }
     }
 
@@ -490,7 +1116,9 @@ impl AudioGraph {
 
         for i in all_numbers {
             if !self.nodes.contains_key(&i) {
-                self.nodes.insert(i.clone(), node);
+                self.nodes.insert(i.clone(), Arc::new(node));
+                Arc::make_mut(&mut self.order).push(i.clone());
+                self.record_change(|| GraphChange::NodeInserted(i.clone()));
                 return i;
             }
         }
@@ -498,3 +1126,60 @@ impl AudioGraph {
         panic!("Index overflow")
     }
 }
+
+/// Declares an [`AudioGraph`], along with it's nodes, ports, and edges, binding every
+/// node and port ID to a local variable of the same name.
+///
+/// Port IDs are allocated in declaration order. An optional latency (a literal, or a parenthesized
+/// expression) can follow the node's name.
+/// Panics if any of the edges can't be inserted.
+///
+/// ```
+/// polygraph::graph! {
+///     graph = {
+///         source: [] => [source_out];
+///         filter @ 64: [filter_in] => [filter_out];
+///         master: [master_in] => [];
+///     }
+///     source.source_out -> filter.filter_in;
+///     filter.filter_out -> master.master_in;
+/// }
+///
+/// assert_eq!(graph[&filter].latency, 64);
+/// let (_num_buffers, schedule) = graph.compile([master]);
+/// assert_eq!(schedule.len(), 3);
+/// ```
+#[macro_export]
+macro_rules! graph {
+    (
+        $graph:ident = {
+            $($node:ident $(@ $latency:tt)? : [$($input:ident),* $(,)?] => [$($output:ident),* $(,)?];)*
+        }
+        $($from:ident . $from_port:ident -> $to:ident . $to_port:ident;)*
+    ) => {
+        let mut $graph = $crate::AudioGraph::default();
+
+        $(
+            let mut node = $crate::Node::default();
+            $(node.latency = $latency;)?
+            $(let $input = node.add_input();)*
+            $(let $output = node.add_output();)*
+            let $node = $graph.insert_node(node);
+        )*
+
+        $(
+            assert!(
+                $graph
+                    .try_insert_edge(
+                        ($from.clone(), $from_port.clone()),
+                        ($to.clone(), $to_port.clone()),
+                    )
+                    .is_ok(),
+                concat!(
+                    "failed to insert edge ",
+                    stringify!($from.$from_port -> $to.$to_port),
+                ),
+            );
+        )*
+    };
+}
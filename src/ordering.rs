@@ -0,0 +1,343 @@
+use super::*;
+
+/// What an [`OrderingHeuristic`] can see of the scheduler's progress.
+pub struct OrderingState<'a> {
+    graph: &'a AudioGraph,
+    transposed: &'a AudioGraph,
+    scheduled: &'a FnvHashSet<NodeID>,
+    last: Option<&'a NodeID>,
+}
+
+impl<'a> OrderingState<'a> {
+    /// The graph being compiled.
+    #[inline]
+    pub fn graph(&self) -> &'a AudioGraph {
+        self.graph
+    }
+
+    #[inline]
+    pub fn is_scheduled(&self, node: &NodeID) -> bool {
+        self.scheduled.contains(node)
+    }
+
+    #[inline]
+    pub fn last_scheduled(&self) -> Option<&'a NodeID> {
+        self.last
+    }
+
+    /// Returns the ports reading from `output` of `node`, ignoring those of nodes not
+    /// part of the schedule.
+    #[inline]
+    pub fn consumers(
+        &self,
+        node: &NodeID,
+        output: &OutputID,
+    ) -> impl Iterator<Item = (&'a NodeID, InputID)> {
+        self.transposed[node]
+            .inputs()
+            .get(&output.clone().transpose())
+            .into_iter()
+            .flat_map(|input| input.connections())
+            .flat_map(|(consumer, ports)| {
+                ports
+                    .iter()
+                    .map(move |port| (consumer, port.clone().transpose()))
+            })
+    }
+}
+
+/// Chooses which node to process next when dependencies allow more than one.
+pub trait OrderingHeuristic {
+    /// Returns the index, in `ready`, of the node to process next, which must be less
+    /// than `ready.len()`. Compilation panics otherwise.
+    ///
+    /// `ready` is never empty, and is kept in the default processing order.
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize;
+}
+
+impl<H: OrderingHeuristic + ?Sized> OrderingHeuristic for &mut H {
+    #[inline]
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize {
+        H::pick(self, state, ready)
+    }
+}
+
+impl<H: OrderingHeuristic + ?Sized> OrderingHeuristic for Box<H> {
+    #[inline]
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize {
+        H::pick(self, state, ready)
+    }
+}
+
+/// Processes nodes with lower IDs first. Since [`AudioGraph::insert_node`] hands out
+/// the lowest free ID, this is insertion order, unless IDs of removed nodes were reused.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InsertionOrder;
+
+impl OrderingHeuristic for InsertionOrder {
+    #[inline]
+    fn pick(&mut self, _state: &OrderingState, ready: &[NodeID]) -> usize {
+        ready
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, id)| id)
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// Processes the most expensive nodes first, according to `cost`, at the given block size.
+pub struct ByCost<F> {
+    pub block_size: usize,
+    pub cost: F,
+}
+
+impl<F: FnMut(&NodeID, &Node) -> Cost> ByCost<F> {
+    #[inline]
+    pub fn new(block_size: usize, cost: F) -> Self {
+        Self { block_size, cost }
+    }
+}
+
+impl<F: FnMut(&NodeID, &Node) -> Cost> OrderingHeuristic for ByCost<F> {
+    #[inline]
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize {
+        let graph = state.graph();
+
+        let mut best = (0, f64::NEG_INFINITY);
+
+        for (i, id) in ready.iter().enumerate() {
+            let cost = (self.cost)(id, &graph[id]).for_block(self.block_size);
+
+            // strict comparison, ties are broken in favor of the default order
+            if cost > best.1 {
+                best = (i, cost);
+            }
+        }
+
+        best.0
+    }
+}
+
+/// Greedily processes the node that leaves the fewest buffers in use, that is, the one
+/// for which the difference between the buffers it's outputs need and the buffers
+/// freed once it has read it's inputs is smallest.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinimizeLiveBuffers;
+
+impl MinimizeLiveBuffers {
+    fn live_buffer_delta(state: &OrderingState, id: &NodeID) -> isize {
+        let node = &state.graph()[id];
+
+        let allocated = node
+            .output_ids()
+            .iter()
+            .filter(|output| state.consumers(id, output).next().is_some())
+            .count();
+
+        let sources = FnvHashSet::from_iter(node.inputs().values().flat_map(|input| {
+            input
+                .connections()
+                .iter()
+                .flat_map(|(source, outputs)| outputs.iter().map(move |output| (source, output)))
+        }));
+
+        let freed = sources
+            .into_iter()
+            .filter(|&(source, output)| {
+                state
+                    .consumers(source, output)
+                    .all(|(consumer, _)| consumer == id || state.is_scheduled(consumer))
+            })
+            .count();
+
+        allocated as isize - freed as isize
+    }
+}
+
+impl OrderingHeuristic for MinimizeLiveBuffers {
+    #[inline]
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize {
+        ready
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, id)| Self::live_buffer_delta(state, id))
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// Processes nodes for which `key` returns equal values (e. g. nodes of the same type)
+/// consecutively. See [`AudioGraph::compile_grouped`].
+///
+/// `key` is called once per node, keys are cached.
+pub struct GroupBy<F, K> {
+    key: F,
+    keys: FnvHashMap<NodeID, K>,
+}
+
+impl<F, K> GroupBy<F, K> {
+    #[inline]
+    pub fn new(key: F) -> Self {
+        Self {
+            key,
+            keys: FnvHashMap::default(),
+        }
+    }
+}
+
+impl<K: Eq, F: FnMut(&NodeID, &Node) -> K> OrderingHeuristic for GroupBy<F, K> {
+    #[inline]
+    fn pick(&mut self, state: &OrderingState, ready: &[NodeID]) -> usize {
+        let graph = state.graph();
+        let Self { key, keys } = self;
+
+        for id in state.last_scheduled().into_iter().chain(ready) {
+            if !keys.contains_key(id) {
+                let node_key = key(id, &graph[id]);
+                keys.insert(id.clone(), node_key);
+            }
+        }
+
+        state
+            .last_scheduled()
+            .and_then(|last| {
+                let last_key = &keys[last];
+                ready.iter().position(|id| &keys[id] == last_key)
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Options for [`AudioGraph::compile_with`].
+#[derive(Default)]
+pub struct CompileOptions<'a> {
+    ordering: Option<Box<dyn OrderingHeuristic + 'a>>,
+    sum_buffers: SumBufferPolicy,
+}
+
+impl<'a> CompileOptions<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `heuristic` to choose between valid processing orders, instead of
+    /// the default, depth-first one.
+    #[inline]
+    pub fn ordering(mut self, heuristic: impl OrderingHeuristic + 'a) -> Self {
+        self.ordering = Some(Box::new(heuristic));
+        self
+    }
+
+    #[inline]
+    pub fn sum_buffers(mut self, policy: SumBufferPolicy) -> Self {
+        self.sum_buffers = policy;
+        self
+    }
+}
+
+impl AudioGraph {
+    /// Like [`Self::compile`], with the given options.
+    #[inline]
+    pub fn compile_with(
+        &self,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+        options: CompileOptions,
+    ) -> (usize, Vec<Task>) {
+        let mut scheduler = self.scheduler(FnvHashSet::from_iter(root_nodes));
+
+        let CompileOptions {
+            ordering,
+            sum_buffers,
+        } = options;
+
+        if let Some(heuristic) = ordering {
+            scheduler.reorder(self, heuristic);
+        }
+
+        let mut schedule = vec![];
+        let num_buffers = scheduler.compile_into(&mut schedule, sum_buffers);
+        (num_buffers, schedule)
+    }
+}
+
+impl Scheduler {
+    /// Reorders `process_order`, within dependency constraints, letting `heuristic`
+    /// choose between the nodes ready to be processed.
+    fn reorder(&mut self, graph: &AudioGraph, mut heuristic: impl OrderingHeuristic) {
+        let Self {
+            transposed,
+            process_order,
+        } = self;
+
+        let consumers = |node: &NodeID| -> FnvHashSet<NodeID> {
+            transposed[node]
+                .inputs()
+                .values()
+                .flat_map(|input| input.connections().keys().cloned())
+                .collect()
+        };
+
+        let mut in_degrees = FnvHashMap::<NodeID, usize>::default();
+
+        for node in process_order.iter() {
+            for consumer in consumers(node) {
+                *in_degrees.entry(consumer).or_default() += 1;
+            }
+        }
+
+        let positions =
+            FnvHashMap::from_iter(process_order.iter().enumerate().map(|(i, n)| (n, i)));
+
+        // kept in the original processing order
+        let mut ready = Vec::from_iter(
+            process_order
+                .iter()
+                .filter(|&node| !in_degrees.contains_key(node))
+                .cloned(),
+        );
+
+        let mut new_order = Vec::with_capacity(process_order.len());
+        let mut scheduled = FnvHashSet::default();
+
+        while !ready.is_empty() {
+            let i = heuristic.pick(
+                &OrderingState {
+                    graph,
+                    transposed,
+                    scheduled: &scheduled,
+                    last: new_order.last(),
+                },
+                &ready,
+            );
+
+            assert!(
+                i < ready.len(),
+                "ordering heuristic picked node {i}, but only {} are ready",
+                ready.len()
+            );
+
+            let node = ready.remove(i);
+
+            for consumer in consumers(&node) {
+                let in_degree = in_degrees.get_mut(&consumer).unwrap();
+                *in_degree -= 1;
+
+                if *in_degree == 0 {
+                    let i = ready.partition_point(|n| positions[n] < positions[&consumer]);
+                    ready.insert(i, consumer);
+                }
+            }
+
+            scheduled.insert(node.clone());
+            new_order.push(node);
+        }
+
+        assert_eq!(
+            new_order.len(),
+            process_order.len(),
+            "INTERNAL ERROR: all nodes must be reordered"
+        );
+
+        *process_order = new_order;
+    }
+}
@@ -0,0 +1,514 @@
+use super::*;
+use core::{
+    cmp::Reverse,
+    fmt::{self, Write},
+};
+
+// Ports are listed in ascending order, buffers are prefixed with `#`
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node {
+                id,
+                inputs,
+                outputs,
+            } => {
+                let mut inputs = Vec::from_iter(inputs);
+                inputs.sort_unstable_by_key(|&(id, _)| id);
+
+                let mut outputs = Vec::from_iter(outputs);
+                outputs.sort_unstable_by_key(|&(id, _)| id);
+
+                write!(f, "node {}", id.0)?;
+
+                f.write_str(" | inputs:")?;
+                for (i, (id, buf)) in inputs.into_iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{sep}{} <- #{buf}", id.0)?;
+                }
+
+                f.write_str(" | outputs:")?;
+                for (i, (id, buf)) in outputs.into_iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{sep}{} -> #{buf}", id.0)?;
+                }
+
+                Ok(())
+            }
+            Self::Sum {
+                left,
+                right,
+                output,
+            } => write!(f, "sum #{left} + #{right} -> #{output}"),
+        }
+    }
+}
+
+/// Returns a listing of `schedule`, with one numbered task per line.
+pub fn pretty_schedule(schedule: &[Task]) -> String {
+    let width = schedule.len().saturating_sub(1).to_string().len();
+
+    let mut out = String::new();
+
+    for (i, task) in schedule.iter().enumerate() {
+        writeln!(out, "{i:>width$}: {task}").unwrap();
+    }
+
+    out
+}
+
+/// Renumbers the buffers used in `schedule` by order of first use.
+///
+/// Buffers used by the same node task are numbered by port ID, inputs first.
+pub fn canonicalize(schedule: &mut [Task]) {
+    let mut mapping = FnvHashMap::default();
+
+    let mut remap = |buf: &mut usize| {
+        let next = mapping.len();
+        *buf = *mapping.entry(*buf).or_insert(next);
+    };
+
+    for task in schedule {
+        match task {
+            Task::Node {
+                inputs, outputs, ..
+            } => {
+                let mut inputs = Vec::from_iter(inputs.iter_mut());
+                inputs.sort_unstable_by_key(|&(id, _)| id);
+                inputs.into_iter().for_each(|(_, buf)| remap(buf));
+
+                let mut outputs = Vec::from_iter(outputs.iter_mut());
+                outputs.sort_unstable_by_key(|&(id, _)| id);
+                outputs.into_iter().for_each(|(_, buf)| remap(buf));
+            }
+            Task::Sum {
+                left,
+                right,
+                output,
+            } => {
+                remap(left);
+                remap(right);
+                remap(output);
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Port {
+    Input(NodeID, InputID),
+    Output(NodeID, OutputID),
+}
+
+fn port_buffers(schedule: &[Task]) -> impl Iterator<Item = (Port, usize)> + '_ {
+    schedule.iter().flat_map(|task| {
+        let Task::Node {
+            id,
+            inputs,
+            outputs,
+        } = task
+        else {
+            return Vec::new();
+        };
+
+        let inputs = inputs
+            .iter()
+            .map(|(input, &buf)| (Port::Input(id.clone(), input.clone()), buf));
+        let outputs = outputs
+            .iter()
+            .map(|(output, &buf)| (Port::Output(id.clone(), output.clone()), buf));
+
+        inputs.chain(outputs).collect()
+    })
+}
+
+/// Renumbers the buffers of `schedule`, which uses `num_buffers` buffers, so that as many
+/// ports as possible use the same buffers as in `previous` (e. g. the schedule compiled
+/// before the graph was last edited). The number of buffers doesn't change.
+pub fn remap_buffers(previous: &[Task], schedule: &mut [Task], num_buffers: usize) {
+    let previous_buffers = FnvHashMap::from_iter(port_buffers(previous));
+
+    let mut votes = FnvHashMap::<(usize, usize), usize>::default();
+
+    for (port, buf) in port_buffers(schedule) {
+        if let Some(&previous_buf) = previous_buffers.get(&port) {
+            if previous_buf < num_buffers {
+                *votes.entry((buf, previous_buf)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut votes = Vec::from_iter(votes);
+    // most votes first, ties broken deterministically
+    votes.sort_unstable_by_key(|&(pair, count)| (Reverse(count), pair));
+
+    let mut mapping = vec![None; num_buffers];
+    let mut taken = vec![false; num_buffers];
+
+    for ((buf, previous_buf), _) in votes {
+        if mapping[buf].is_none() && !taken[previous_buf] {
+            mapping[buf] = Some(previous_buf);
+            taken[previous_buf] = true;
+        }
+    }
+
+    let mut free = (0..num_buffers).filter(|&i| !taken[i]);
+
+    let mapping = Vec::from_iter(
+        mapping
+            .into_iter()
+            .map(|buf| buf.unwrap_or_else(|| free.next().unwrap())),
+    );
+
+    for task in schedule {
+        match task {
+            Task::Node {
+                inputs, outputs, ..
+            } => {
+                for buf in inputs.values_mut().chain(outputs.values_mut()) {
+                    *buf = mapping[*buf];
+                }
+            }
+            Task::Sum {
+                left,
+                right,
+                output,
+            } => {
+                for buf in [left, right, output] {
+                    *buf = mapping[*buf];
+                }
+            }
+        }
+    }
+}
+
+type Signal = Vec<(NodeID, OutputID)>;
+
+/// Runs a schedule symbolically, recording, for every node task, which output ports
+/// have been summed into each of it's input ports.
+#[derive(Default, PartialEq, Eq, Debug)]
+struct SymbolicRunner {
+    buffers: FnvHashMap<usize, Signal>,
+    received: FnvHashMap<NodeID, Vec<FnvHashMap<InputID, Signal>>>,
+}
+
+impl TaskRunner for SymbolicRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        let inputs = inputs
+            .iter()
+            .map(|(input, buf)| {
+                let signal = self.buffers.get(buf).cloned().unwrap_or_default();
+                (input.clone(), signal)
+            })
+            .collect();
+
+        self.received.entry(id.clone()).or_default().push(inputs);
+
+        for (output, &buf) in outputs {
+            self.buffers.insert(buf, vec![(id.clone(), output.clone())]);
+        }
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        let mut signal = self.buffers.get(&left).cloned().unwrap_or_default();
+        signal.extend(self.buffers.get(&right).into_iter().flatten().cloned());
+        signal.sort_unstable();
+        self.buffers.insert(output, signal);
+    }
+}
+
+fn node_task<'a>(schedule: &'a [Task], node: &NodeID) -> Option<&'a Task> {
+    schedule
+        .iter()
+        .find(|task| matches!(task, Task::Node { id, .. } if id == node))
+}
+
+/// Returns the index of the buffer read by `node` on it's input port `input`.
+pub fn input_buffer_of(schedule: &[Task], node: &NodeID, input: &InputID) -> Option<usize> {
+    let Some(Task::Node { inputs, .. }) = node_task(schedule, node) else {
+        return None;
+    };
+
+    inputs.get(input).copied()
+}
+
+/// Returns the index of the buffer written by `node` on it's output port `output`.
+pub fn output_buffer_of(schedule: &[Task], node: &NodeID, output: &OutputID) -> Option<usize> {
+    let Some(Task::Node { outputs, .. }) = node_task(schedule, node) else {
+        return None;
+    };
+
+    outputs.get(output).copied()
+}
+
+/// Returns the output ports whose signals end up, after being summed together,
+/// in the buffer read by `node` on it's input port `input`, sorted.
+pub fn source_chain(
+    schedule: &[Task],
+    node: &NodeID,
+    input: &InputID,
+) -> Option<Vec<(NodeID, OutputID)>> {
+    let mut runner = SymbolicRunner::default();
+
+    for task in schedule {
+        if let Task::Node { id, inputs, .. } = task {
+            if id == node {
+                let buf = inputs.get(input)?;
+                return Some(runner.buffers.get(buf).cloned().unwrap_or_default());
+            }
+        }
+
+        task.run(&mut runner);
+    }
+
+    None
+}
+
+fn semantics(schedule: &[Task]) -> FnvHashMap<NodeID, Vec<FnvHashMap<InputID, Signal>>> {
+    let mut runner = SymbolicRunner::default();
+    run_schedule(schedule, &mut runner);
+    runner.received
+}
+
+/// Returns whether both schedules feed every node the same combination of
+/// output ports on each input, regardless of task order and buffer numbering.
+pub fn schedules_equivalent(a: &[Task], b: &[Task]) -> bool {
+    semantics(a) == semantics(b)
+}
+
+/// # Panics
+///
+/// If `a` and `b` are not equivalent, according to [`schedules_equivalent`]
+#[track_caller]
+pub fn assert_schedule_equiv(a: &[Task], b: &[Task]) {
+    assert!(
+        schedules_equivalent(a, b),
+        "schedules are not equivalent\n left: {a:#?}\nright: {b:#?}"
+    );
+}
+
+/// A way in which a schedule doesn't correctly process a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    BufferOutOfRange {
+        task: usize,
+        buffer: usize,
+    },
+    /// A node that needs to be processed isn't
+    MissingNode(NodeID),
+    /// A node is processed more than once, or despite not needing to be
+    UnexpectedNode(NodeID),
+    /// A node doesn't receive the right signals on one of it's inputs
+    WrongInput {
+        node: NodeID,
+        input: InputID,
+        expected: Vec<(NodeID, OutputID)>,
+        found: Vec<(NodeID, OutputID)>,
+    },
+}
+
+/// Checks that `schedule`, using `num_buffers` buffers, processes all nodes `root_nodes`
+/// depend on, exactly once, each of them after their dependencies, with every input port
+/// receiving the sum of all the output ports it is connected to.
+///
+/// # Panics
+///
+/// if no node exists for any of the ids in `root_nodes`
+pub fn validate_schedule(
+    graph: &AudioGraph,
+    root_nodes: impl IntoIterator<Item = NodeID>,
+    num_buffers: usize,
+    schedule: &[Task],
+) -> Result<(), ScheduleError> {
+    for (i, task) in schedule.iter().enumerate() {
+        let buffers: Vec<usize> = match task {
+            Task::Node {
+                inputs, outputs, ..
+            } => inputs.values().chain(outputs.values()).copied().collect(),
+            Task::Sum {
+                left,
+                right,
+                output,
+            } => vec![*left, *right, *output],
+        };
+
+        if let Some(&buffer) = buffers.iter().find(|&&buf| buf >= num_buffers) {
+            return Err(ScheduleError::BufferOutOfRange { task: i, buffer });
+        }
+    }
+
+    let mut required = vec![];
+    let mut visited = FnvHashSet::default();
+    for root in root_nodes {
+        graph.visit_post_order(&root, &mut visited, &mut required);
+    }
+
+    let mut received = semantics(schedule);
+
+    for node in required {
+        let Some(mut invocations) = received.remove(&node) else {
+            return Err(ScheduleError::MissingNode(node));
+        };
+
+        if invocations.len() > 1 {
+            return Err(ScheduleError::UnexpectedNode(node));
+        }
+
+        let mut found_inputs = invocations.pop().unwrap();
+
+        for (input, connections) in graph[&node].inputs() {
+            let mut expected = Vec::from_iter(connections.connections().iter().flat_map(
+                |(source, outputs)| {
+                    outputs
+                        .iter()
+                        .map(|output| (source.clone(), output.clone()))
+                },
+            ));
+            expected.sort_unstable();
+
+            let found = found_inputs.remove(input).unwrap_or_default();
+
+            if found != expected {
+                return Err(ScheduleError::WrongInput {
+                    node,
+                    input: input.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        if let Some((input, found)) = found_inputs.into_iter().next() {
+            return Err(ScheduleError::WrongInput {
+                node,
+                input,
+                expected: vec![],
+                found,
+            });
+        }
+    }
+
+    if let Some(node) = received.into_keys().next() {
+        return Err(ScheduleError::UnexpectedNode(node));
+    }
+
+    Ok(())
+}
+
+/// Dependencies between the tasks of a schedule, see [`task_graph`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskGraph {
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl TaskGraph {
+    /// The number of tasks.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    /// Indices of the tasks that must complete before task `task` starts, sorted.
+    #[inline]
+    pub fn dependencies(&self, task: usize) -> &[usize] {
+        &self.dependencies[task]
+    }
+
+    /// Every `(dependency, dependent)` pair.
+    #[inline]
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.dependencies
+            .iter()
+            .enumerate()
+            .flat_map(|(task, deps)| deps.iter().map(move |&dep| (dep, task)))
+    }
+
+    /// Returns whether running the tasks in `order` (a permutation of task indices)
+    /// respects all dependencies.
+    #[inline]
+    pub fn is_topological_order(&self, order: &[usize]) -> bool {
+        if order.len() != self.len() {
+            return false;
+        }
+
+        let mut positions = vec![None; self.len()];
+
+        for (position, &task) in order.iter().enumerate() {
+            match positions.get_mut(task) {
+                Some(slot @ None) => *slot = Some(position),
+                _ => return false,
+            }
+        }
+
+        self.edges()
+            .all(|(dep, task)| positions[dep] < positions[task])
+    }
+}
+
+/// Returns the dependencies between the tasks of `schedule`, for executors running
+/// independent tasks concurrently.
+///
+/// Since buffers are reused, a task depends not only on the tasks writing the buffers it
+/// reads, but also on the earlier tasks reading or writing the buffers it writes.
+pub fn task_graph(schedule: &[Task]) -> TaskGraph {
+    let mut last_writer = FnvHashMap::<usize, usize>::default();
+    let mut readers = FnvHashMap::<usize, Vec<usize>>::default();
+
+    let dependencies = schedule
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let (reads, writes) = match task {
+                Task::Node {
+                    inputs, outputs, ..
+                } => (
+                    Vec::from_iter(inputs.values().copied()),
+                    Vec::from_iter(outputs.values().copied()),
+                ),
+                Task::Sum {
+                    left,
+                    right,
+                    output,
+                } => (vec![*left, *right], vec![*output]),
+            };
+
+            let mut deps = vec![];
+
+            for buf in &reads {
+                deps.extend(last_writer.get(buf));
+            }
+
+            for buf in &writes {
+                deps.extend(last_writer.get(buf));
+                deps.extend(readers.get(buf).into_iter().flatten());
+            }
+
+            for buf in reads {
+                readers.entry(buf).or_default().push(i);
+            }
+
+            for buf in writes {
+                last_writer.insert(buf, i);
+                readers.remove(&buf);
+            }
+
+            // tasks may read the buffers they write
+            deps.retain(|&dep| dep != i);
+            deps.sort_unstable();
+            deps.dedup();
+            deps
+        })
+        .collect();
+
+    TaskGraph { dependencies }
+}
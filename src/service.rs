@@ -0,0 +1,169 @@
+use super::*;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread::{self, JoinHandle},
+};
+
+struct CompileRequest {
+    id: u64,
+    graph: GraphSnapshot,
+    root_nodes: Vec<NodeID>,
+}
+
+/// A schedule compiled by a [`CompileService`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompiledSchedule {
+    /// The id returned by the [`CompileService::request`] call this schedule results from
+    pub request: u64,
+    pub num_buffers: usize,
+    pub schedule: Vec<Task>,
+}
+
+/// Why a [`CompileService`] couldn't deliver a schedule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    /// One of the root nodes of request `request` doesn't exist in it's graph
+    MissingRootNode { request: u64, node: NodeID },
+    /// The worker thread stopped (after panicking), no more requests can be compiled
+    WorkerStopped,
+}
+
+/// Compiles graph snapshots on a worker thread.
+///
+/// Every new request supersedes the previous ones: pending requests are dropped before
+/// being compiled, and those that are already being compiled are not delivered.
+/// Results are always delivered in request order. Requests that can't be compiled
+/// are reported as failed, and don't stop the service.
+pub struct CompileService {
+    requests: Option<Sender<CompileRequest>>,
+    results: Receiver<Result<CompiledSchedule, CompileError>>,
+    latest: Arc<AtomicU64>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Default for CompileService {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompileService {
+    #[inline]
+    pub fn new() -> Self {
+        let (requests, request_receiver) = mpsc::channel::<CompileRequest>();
+        let (result_sender, results) = mpsc::channel();
+        let latest = Arc::new(AtomicU64::new(0));
+
+        let worker = thread::spawn({
+            let latest = latest.clone();
+
+            move || {
+                while let Ok(mut request) = request_receiver.recv() {
+                    // skip to the latest pending request
+                    while let Ok(newer) = request_receiver.try_recv() {
+                        request = newer;
+                    }
+
+                    let CompileRequest {
+                        id,
+                        graph,
+                        root_nodes,
+                    } = request;
+
+                    let missing = root_nodes
+                        .iter()
+                        .find(|root| graph.get_node(root).is_none());
+
+                    let result = match missing {
+                        Some(node) => Err(CompileError::MissingRootNode {
+                            request: id,
+                            node: node.clone(),
+                        }),
+                        None => {
+                            let (num_buffers, schedule) = graph.compile(root_nodes);
+
+                            Ok(CompiledSchedule {
+                                request: id,
+                                num_buffers,
+                                schedule,
+                            })
+                        }
+                    };
+
+                    if latest.load(Ordering::Acquire) != id {
+                        continue;
+                    }
+
+                    if result_sender.send(result).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            requests: Some(requests),
+            results,
+            latest,
+            worker: Some(worker),
+        }
+    }
+
+    /// Requests the compilation of `graph`, cancelling all previous requests,
+    /// and returns this request's id.
+    ///
+    /// Fails with [`CompileError::WorkerStopped`] if the worker thread stopped.
+    #[inline]
+    pub fn request(
+        &mut self,
+        graph: GraphSnapshot,
+        root_nodes: impl IntoIterator<Item = NodeID>,
+    ) -> Result<u64, CompileError> {
+        let id = self.latest.load(Ordering::Relaxed) + 1;
+        self.latest.store(id, Ordering::Release);
+
+        self.requests
+            .as_ref()
+            .unwrap()
+            .send(CompileRequest {
+                id,
+                graph,
+                root_nodes: Vec::from_iter(root_nodes),
+            })
+            .map_err(|_| CompileError::WorkerStopped)?;
+
+        Ok(id)
+    }
+
+    /// Returns the result of a request, if one is available, without blocking.
+    #[inline]
+    pub fn try_recv(&self) -> Option<Result<CompiledSchedule, CompileError>> {
+        match self.results.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(CompileError::WorkerStopped)),
+        }
+    }
+
+    /// Blocks until the result of a request is available.
+    #[inline]
+    pub fn recv(&self) -> Result<CompiledSchedule, CompileError> {
+        self.results
+            .recv()
+            .unwrap_or(Err(CompileError::WorkerStopped))
+    }
+}
+
+impl Drop for CompileService {
+    fn drop(&mut self) {
+        drop(self.requests.take());
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use super::*;
+use core::ops::Deref;
+
+/// A read-only, copy-on-write, snapshot of an [`AudioGraph`].
+///
+/// Taking a snapshot doesn't clone any node, and the original graph only clones those it
+/// edits afterwards, so snapshots are cheap enough to be taken after every edit, and sent
+/// to another thread to be compiled there, while the original graph keeps being edited.
+#[derive(Clone, Debug)]
+pub struct GraphSnapshot(AudioGraph);
+
+impl Deref for GraphSnapshot {
+    type Target = AudioGraph;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl GraphSnapshot {
+    /// Returns an editable graph, starting with the contents of this snapshot.
+    #[inline]
+    pub fn into_graph(self) -> AudioGraph {
+        self.0
+    }
+}
+
+impl AudioGraph {
+    #[inline]
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot(Self {
+            nodes: self.nodes.clone(),
+            order: self.order.clone(),
+            changes: None,
+        })
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// A [`TaskRunner`] for [`TappedSchedule`]s.
+pub trait TapRunner: TaskRunner {
+    /// Called right after the task writing buffer `buffer`, which holds the signal
+    /// tapped by tap `tap`, has run. Typically copies the buffer into a [`TapRing`].
+    fn tap(&mut self, tap: usize, buffer: usize);
+}
+
+/// A compiled schedule with taps on some output ports, e. g. for oscilloscopes, that
+/// runners are notified of right after the port's node task has run.
+///
+/// Taps keep their indices when the schedule is swapped for a recompiled one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TappedSchedule {
+    schedule: Vec<Task>,
+    taps: Vec<(NodeID, OutputID)>,
+    // for every task, the taps it feeds, and their buffers
+    tapped: Vec<Vec<(usize, usize)>>,
+}
+
+impl TappedSchedule {
+    #[inline]
+    pub fn new(schedule: Vec<Task>, taps: impl IntoIterator<Item = (NodeID, OutputID)>) -> Self {
+        let mut tapped = Self {
+            schedule,
+            taps: Vec::from_iter(taps),
+            tapped: vec![],
+        };
+
+        tapped.resolve();
+        tapped
+    }
+
+    #[inline]
+    pub fn schedule(&self) -> &[Task] {
+        &self.schedule
+    }
+
+    #[inline]
+    pub fn taps(&self) -> &[(NodeID, OutputID)] {
+        &self.taps
+    }
+
+    /// Returns whether the port of tap `tap` is written to by the current schedule. Taps on
+    /// ports of nodes that aren't scheduled, or that aren't connected, aren't.
+    #[inline]
+    pub fn is_active(&self, tap: usize) -> bool {
+        self.tapped.iter().flatten().any(|&(t, _)| t == tap)
+    }
+
+    /// Replaces the schedule (e. g. with a recompiled one), keeping the taps, returns
+    /// the previous one.
+    #[inline]
+    pub fn swap_schedule(&mut self, schedule: Vec<Task>) -> Vec<Task> {
+        let previous = mem::replace(&mut self.schedule, schedule);
+        self.resolve();
+        previous
+    }
+
+    /// Replaces the taps, returns the previous ones.
+    #[inline]
+    pub fn set_taps(
+        &mut self,
+        taps: impl IntoIterator<Item = (NodeID, OutputID)>,
+    ) -> Vec<(NodeID, OutputID)> {
+        let previous = mem::replace(&mut self.taps, Vec::from_iter(taps));
+        self.resolve();
+        previous
+    }
+
+    fn resolve(&mut self) {
+        self.tapped =
+            self.schedule
+                .iter()
+                .map(|task| {
+                    let Task::Node { id, outputs, .. } = task else {
+                        return vec![];
+                    };
+
+                    Vec::from_iter(self.taps.iter().enumerate().filter_map(
+                        |(tap, (node, output))| {
+                            (node == id)
+                                .then(|| outputs.get(output))
+                                .flatten()
+                                .map(|&buffer| (tap, buffer))
+                        },
+                    ))
+                })
+                .collect();
+    }
+
+    /// Returns the schedule, and the hook notifying runners of it's taps, to run it along
+    /// with other hooks, through [`run_schedule_with`].
+    #[inline]
+    pub fn hook(&self) -> (&[Task], TapHook<'_>) {
+        (&self.schedule, TapHook(&self.tapped))
+    }
+
+    /// Runs every task, in order, for one block. Doesn't allocate.
+    #[inline]
+    pub fn run(&self, runner: &mut (impl TapRunner + ?Sized)) {
+        let (schedule, mut hook) = self.hook();
+        run_schedule_with(schedule, runner, &mut hook);
+    }
+}
+
+/// The [`TaskHook`] of a [`TappedSchedule`], see [`TappedSchedule::hook`].
+///
+/// Taps of skipped tasks are still notified, skipping hooks write to their outputs.
+#[derive(Clone, Copy, Debug)]
+pub struct TapHook<'a>(&'a [Vec<(usize, usize)>]);
+
+impl<R: TapRunner + ?Sized> TaskHook<R> for TapHook<'_> {
+    #[inline]
+    fn after(&mut self, index: usize, _task: &Task, _ran: bool, runner: &mut R) {
+        for &(tap, buffer) in &self.0[index] {
+            runner.tap(tap, buffer);
+        }
+    }
+}
+
+/// A lock-free ring of the most recent samples of a tapped signal, optionally decimated,
+/// written to by the audio thread and read by a UI thread, neither of which ever blocks.
+///
+/// If the writer wraps around the ring while it's being read, the read frame can mix
+/// older and newer samples, which is harmless for display purposes.
+#[derive(Debug)]
+pub struct TapRing {
+    // `f32` bits
+    samples: Box<[AtomicU32]>,
+    decimation: usize,
+    // samples pushed so far, before decimation
+    pushed: AtomicUsize,
+}
+
+impl TapRing {
+    /// Creates a ring holding the last `capacity` samples kept, keeping one sample
+    /// out of every `decimation`.
+    ///
+    /// # Panics
+    ///
+    /// if `capacity` or `decimation` is `0`
+    #[inline]
+    pub fn new(capacity: usize, decimation: usize) -> Self {
+        assert_ne!(capacity, 0, "tap rings must hold at least one sample");
+        assert_ne!(decimation, 0, "decimation factors must be positive");
+
+        Self {
+            samples: Box::from_iter(iter::repeat_with(AtomicU32::default).take(capacity)),
+            decimation,
+            pushed: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    #[inline]
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    /// Appends `samples`, overwriting the oldest ones. Must only be called from one thread
+    /// at a time. Doesn't allocate.
+    #[inline]
+    pub fn push(&self, samples: &[f32]) {
+        let pushed = self.pushed.load(Ordering::Relaxed);
+
+        for (i, &sample) in iter::zip(pushed.., samples) {
+            if i % self.decimation == 0 {
+                let slot = i / self.decimation % self.capacity();
+                self.samples[slot].store(sample.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        self.pushed.store(pushed + samples.len(), Ordering::Release);
+    }
+
+    /// Copies the most recent samples into `frame`, oldest first, returns how many were
+    /// copied, which is less than `frame.len()` if the ring doesn't hold enough samples.
+    #[inline]
+    pub fn read_latest(&self, frame: &mut [f32]) -> usize {
+        let kept = self
+            .pushed
+            .load(Ordering::Acquire)
+            .div_ceil(self.decimation);
+        let len = frame.len().min(kept).min(self.capacity());
+
+        for (sample, i) in iter::zip(&mut frame[..len], kept - len..) {
+            *sample = f32::from_bits(self.samples[i % self.capacity()].load(Ordering::Relaxed));
+        }
+
+        len
+    }
+}
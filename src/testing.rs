@@ -0,0 +1,227 @@
+//! Generators for standard graph topologies, for use in tests, benchmarks and fuzzers.
+
+use super::*;
+use core::{convert::identity, iter::zip, ops::Range};
+
+/// A generated graph, along with the root nodes it should be compiled with.
+#[derive(Clone, Debug, Default)]
+pub struct TestGraph {
+    pub graph: AudioGraph,
+    /// The generated graph's sinks
+    pub roots: Vec<NodeID>,
+}
+
+impl TestGraph {
+    #[inline]
+    pub fn compile(&self) -> (usize, Vec<Task>) {
+        self.graph.compile(self.roots.iter().cloned())
+    }
+
+    fn node(&mut self, inputs: usize, outputs: usize, latency: u64) -> NodeID {
+        let mut node = Node::default();
+        node.add_input_bus(inputs);
+        node.add_output_bus(outputs);
+        node.latency = latency;
+        self.graph.insert_node(node)
+    }
+
+    fn connect(&mut self, from: &NodeID, output: usize, to: &NodeID, input: usize) {
+        // ports are allocated in order, starting at 0
+        let from_port = (from.clone(), OutputID(output as u32));
+        let to_port = (to.clone(), InputID(input as u32));
+
+        assert!(
+            self.graph.try_insert_edge(from_port, to_port).is_ok(),
+            "generated graphs must be acyclic"
+        );
+    }
+}
+
+/// `len` nodes, each feeding the next one, all with the given latency.
+#[inline]
+pub fn chain(len: usize, latency: u64) -> TestGraph {
+    let mut test = TestGraph::default();
+
+    let mut previous = None;
+
+    for i in 0..len {
+        let is_last = i + 1 == len;
+        let node = test.node(previous.is_some().into(), (!is_last).into(), latency);
+
+        if let Some(previous) = previous.replace(node.clone()) {
+            test.connect(&previous, 0, &node, 0);
+        }
+    }
+
+    test.roots.extend(previous);
+    test
+}
+
+/// A source feeding `width` parallel nodes, all of which feed the same input of a sink.
+#[inline]
+pub fn diamond(width: usize) -> TestGraph {
+    let mut test = TestGraph::default();
+
+    let source = test.node(0, 1, 0);
+    let sink = test.node(1, 0, 0);
+
+    for _ in 0..width {
+        let middle = test.node(1, 1, 0);
+        test.connect(&source, 0, &middle, 0);
+        test.connect(&middle, 0, &sink, 0);
+    }
+
+    test.roots.push(sink);
+    test
+}
+
+/// `sources` sources and `sources + 1` sinks, where source `i` feeds sinks `i` and `i + 1`.
+#[inline]
+pub fn m_shape(sources: usize) -> TestGraph {
+    let mut test = TestGraph::default();
+
+    let sources = Vec::from_iter((0..sources).map(|_| test.node(0, 1, 0)));
+    let sinks = Vec::from_iter((0..=sources.len()).map(|_| test.node(1, 0, 0)));
+
+    for (i, source) in sources.iter().enumerate() {
+        test.connect(source, 0, &sinks[i], 0);
+        test.connect(source, 0, &sinks[i + 1], 0);
+    }
+
+    test.roots = sinks;
+    test
+}
+
+/// `sinks + 1` sources and `sinks` sinks, where sink `i` is fed by sources `i` and `i + 1`.
+#[inline]
+pub fn w_shape(sinks: usize) -> TestGraph {
+    let mut test = TestGraph::default();
+
+    let sources = Vec::from_iter((0..=sinks).map(|_| test.node(0, 1, 0)));
+    let sinks = Vec::from_iter((0..sinks).map(|_| test.node(1, 0, 0)));
+
+    for (i, sink) in sinks.iter().enumerate() {
+        test.connect(&sources[i], 0, sink, 0);
+        test.connect(&sources[i + 1], 0, sink, 0);
+    }
+
+    test.roots = sinks;
+    test
+}
+
+/// `sources` sources, with `channels` outputs each, all feeding the `channels`
+/// inputs of a single mixer.
+#[inline]
+pub fn dense_mixer(sources: usize, channels: usize) -> TestGraph {
+    let mut test = TestGraph::default();
+
+    let mixer = test.node(channels, 0, 0);
+
+    for _ in 0..sources {
+        let source = test.node(0, channels, 0);
+
+        for channel in 0..channels {
+            test.connect(&source, channel, &mixer, channel);
+        }
+    }
+
+    test.roots.push(mixer);
+    test
+}
+
+/// Parameters of a random directed acyclic graph, see [`random_dag`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RandomDag {
+    pub nodes: usize,
+    /// Maximum number of edges into each node
+    pub max_fan_in: usize,
+    /// Maximum number of edges out of each node
+    pub max_fan_out: usize,
+    /// Latencies are uniformly distributed in this range, which must not be empty
+    pub latency: Range<u64>,
+    pub seed: u64,
+}
+
+impl Default for RandomDag {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            nodes: 32,
+            max_fan_in: 3,
+            max_fan_out: 3,
+            latency: 0..1,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+// xorshift64, the state must never be zero
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        let Self(state) = self;
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// A random graph, where every node has one input and one output, and edges only go
+/// from nodes to ones inserted after them. The same parameters always produce the same graph.
+#[inline]
+pub fn random_dag(params: &RandomDag) -> TestGraph {
+    let RandomDag {
+        nodes,
+        max_fan_in,
+        max_fan_out,
+        ref latency,
+        seed,
+    } = *params;
+
+    assert!(!latency.is_empty(), "latency range must not be empty");
+
+    let mut rng = Rng(seed.max(1));
+    let mut test = TestGraph::default();
+
+    let mut ids = Vec::<NodeID>::with_capacity(nodes);
+    let mut fan_outs = Vec::with_capacity(nodes);
+
+    for i in 0..nodes {
+        let latency = latency.start + rng.below(latency.end - latency.start);
+        let id = test.node(1, 1, latency);
+
+        if i != 0 {
+            let fan_in = rng.below(max_fan_in as u64 + 1);
+
+            for _ in 0..fan_in {
+                let source = rng.below(i as u64) as usize;
+
+                if fan_outs[source] < max_fan_out
+                    && test
+                        .graph
+                        .try_insert_edge(
+                            (ids[source].clone(), OutputID(0)),
+                            (id.clone(), InputID(0)),
+                        )
+                        .is_ok_and(identity)
+                {
+                    fan_outs[source] += 1;
+                }
+            }
+        }
+
+        ids.push(id);
+        fan_outs.push(0);
+    }
+
+    test.roots =
+        Vec::from_iter(zip(ids, fan_outs).filter_map(|(id, fan_out)| (fan_out == 0).then_some(id)));
+
+    test
+}
@@ -1,10 +1,16 @@
 use super::*;
+use alloc_guard::*;
 use core::{array, convert::identity as id, iter::zip, ops::Not};
+use std::alloc::System;
+
+#[global_allocator]
+static ALLOCATOR: GuardedAllocator<System> = GuardedAllocator(System);
 
 // These tests aren't ideal, I have to print the compiled schedule and review it first,
 // then insert it as the rhs of the final assert directive if it's correct. This is inconvenient,
 // since there are usually many correct schedules, and any update to the graph's traversal order
 // will break these tests, in spite of, theoretically, still creating correct schedules.
+// Tests using `assert_schedule_equiv` only compare what each node receives, and are immune to this.
 
 #[test]
 fn basic_cycle() {
@@ -88,54 +94,27 @@ fn test_basic() {
 
 #[test]
 fn test_chain() {
-    let mut graph = AudioGraph::default();
-
-    let mut master = Node::default();
-    let master_input_id = master.add_input();
-    let master_id = graph.insert_node(master);
-
-    let mut node1 = Node::default();
-    let node1_output_id = node1.add_output();
-    let node1_id = graph.insert_node(node1);
-
-    let mut node2 = Node::default();
-    let node2_output_id = node2.add_output();
-    let node2_input_id = node2.add_input();
-    let node2_id = graph.insert_node(node2);
-
-    let mut node3 = Node::default();
-    let node3_output_id = node3.add_output();
-    let node3_input_id = node3.add_input();
-    let node3_id = graph.insert_node(node3);
-
-    assert!(graph
-        .try_insert_edge(
-            (node1_id.clone(), node1_output_id.clone()),
-            (node2_id.clone(), node2_input_id.clone())
-        )
-        .is_ok_and(id));
-    assert!(graph
-        .try_insert_edge(
-            (node2_id.clone(), node2_output_id.clone()),
-            (node3_id.clone(), node3_input_id.clone())
-        )
-        .is_ok_and(id));
-    assert!(graph
-        .try_insert_edge(
-            (node3_id.clone(), node3_output_id.clone()),
-            (master_id.clone(), master_input_id.clone())
-        )
-        .is_ok_and(id));
+    graph! {
+        graph = {
+            master: [master_input_id] => [];
+            node1: [] => [node1_output_id];
+            node2: [node2_input_id] => [node2_output_id];
+            node3: [node3_input_id] => [node3_output_id];
+        }
+        node1.node1_output_id -> node2.node2_input_id;
+        node2.node2_output_id -> node3.node3_input_id;
+        node3.node3_output_id -> master.master_input_id;
+    }
 
-    let (num_buffers, schedule) = graph.compile([master_id.clone()]);
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
 
     assert_eq!(
         schedule,
         &[
-            Task::node(node1_id, [], [(node1_output_id, 0)]),
-            Task::node(node2_id, [(node2_input_id, 0)], [(node2_output_id, 0)]),
-            Task::node(node3_id, [(node3_input_id, 0)], [(node3_output_id, 0)]),
-            Task::node(master_id, [(master_input_id, 0)], []),
+            Task::node(node1, [], [(node1_output_id, 0)]),
+            Task::node(node2, [(node2_input_id, 0)], [(node2_output_id, 0)]),
+            Task::node(node3, [(node3_input_id, 0)], [(node3_output_id, 0)]),
+            Task::node(master, [(master_input_id, 0)], []),
         ]
     );
 
@@ -221,16 +200,14 @@ fn test_adder() {
 
     let (num_buffers, schedule) = graph.compile([master_id.clone()]);
 
-    // println!("{schedule:#?}");
-
-    assert_eq!(
-        schedule,
-        [
+    assert_schedule_equiv(
+        &schedule,
+        &[
             Task::node(left_id, [], [(left_output_id, 0)]),
             Task::node(right_id, [], [(right_output_id, 1)]),
             Task::sum(1, 0, 0),
             Task::node(master_id, [(master_input_id, 0)], []),
-        ]
+        ],
     );
 
     assert_eq!(num_buffers, 2);
@@ -260,21 +237,19 @@ fn test_multiple_adders() {
 
     let (num_buffers, schedule) = graph.compile([master_id.clone()]);
 
-    // println!("{schedule:#?}");
-
     let [(node_a_output_id, node_a_id), (node_b_output_id, node_b_id), (node_c_output_id, node_c_id)] =
         nodes;
 
-    assert_eq!(
-        schedule,
-        [
+    assert_schedule_equiv(
+        &schedule,
+        &[
             Task::node(node_a_id, [], [(node_a_output_id, 0)]),
             Task::node(node_c_id, [], [(node_c_output_id, 1)]),
             Task::sum(1, 0, 0),
             Task::node(node_b_id, [], [(node_b_output_id, 1)]),
             Task::sum(1, 0, 0),
             Task::node(master_id, [(master_input, 0)], []),
-        ]
+        ],
     );
 
     assert_eq!(num_buffers, 2);
@@ -345,8 +320,6 @@ fn test_m_graph() {
 
     let (num_buffers, schedule) = graph.compile(master_ids.clone());
 
-    // println!("{schedule:#?}");
-
     let [master1, master2, master3] = master_ids;
     let [master1_input, master2_input, master3_input] = master_input_ids;
 
@@ -364,9 +337,9 @@ fn test_m_graph() {
 
     // assert_eq!(num_buffers, 3);
 
-    assert_eq!(
-        schedule,
-        [
+    assert_schedule_equiv(
+        &schedule,
+        &[
             Task::node(n1_id, [], [(n1_output_id, 0)]),
             Task::node(master2, [(master2_input, 0)], []),
             Task::node(n2_id, [], [(n2_output_id, 1)]),
@@ -416,20 +389,2309 @@ fn mutiple_input_ports() {
 
     let (num_buffers, schedule) = graph.compile([master_id.clone()]);
 
-    // println!("{schedule:#?}");
-
-    assert_eq!(
-        schedule,
-        [
+    assert_schedule_equiv(
+        &schedule,
+        &[
             Task::node(source_node_id, [], [(source_node_output_id, 0)]),
             Task::node(
                 sink_node_id,
                 sink_node_input_ids.map(|id| (id, 0)),
-                [(sink_node_output_id, 0)]
+                [(sink_node_output_id, 0)],
             ),
             Task::node(master_id, [(master_input_id, 0)], []),
+        ],
+    );
+
+    assert_eq!(num_buffers, 1);
+}
+
+#[derive(Default)]
+struct SummingRunner {
+    buffers: Vec<u64>,
+    received: FnvHashMap<NodeID, u64>,
+}
+
+impl TaskRunner for SummingRunner {
+    // each node outputs the sum of it's inputs, plus a value unique to it
+
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        let input_sum = inputs.values().map(|&i| self.buffers[i]).sum();
+        self.received.insert(id.clone(), input_sum);
+
+        for &i in outputs.values() {
+            self.buffers[i] = input_sum + (1 << id.0);
+        }
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        self.buffers[output] = self.buffers[left] + self.buffers[right];
+    }
+}
+
+impl CompactTaskRunner for SummingRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &[(InputID, usize)],
+        outputs: &[(OutputID, usize)],
+    ) {
+        let input_sum = inputs.iter().map(|&(_, i)| self.buffers[i]).sum();
+        self.received.insert(id.clone(), input_sum);
+
+        for &(_, i) in outputs {
+            self.buffers[i] = input_sum + (1 << id.0);
+        }
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        self.buffers[output] = self.buffers[left] + self.buffers[right];
+    }
+}
+
+struct SleepingRunner {
+    slow_node: NodeID,
+    sleep: std::time::Duration,
+}
+
+impl TaskRunner for SleepingRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        _inputs: &FnvHashMap<InputID, usize>,
+        _outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        if id == &self.slow_node {
+            std::thread::sleep(self.sleep);
+        }
+    }
+
+    fn run_sum(&mut self, _left: usize, _right: usize, _output: usize) {}
+}
+
+#[test]
+fn deadline_misses() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            slow: [slow_in] => [slow_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> slow.slow_in;
+        slow.slow_out -> master.master_in;
+    }
+
+    let (_, schedule) = graph.compile([master]);
+    let slow_task = schedule
+        .iter()
+        .position(|task| matches!(task, Task::Node { id, .. } if id == &slow))
+        .unwrap();
+
+    let mut runner = SleepingRunner {
+        slow_node: slow,
+        sleep: std::time::Duration::from_millis(5),
+    };
+
+    let mut monitor = DeadlineMonitor::new(1, 48000.);
+
+    // one second long blocks
+    assert!(monitor.run_block(&schedule, &mut runner, 48000));
+    assert_eq!(monitor.misses().count(), 0);
+
+    assert!(!monitor.run_block(&schedule, &mut runner, 64));
+    assert!(!monitor.run_block(&schedule, &mut runner, 64));
+    assert_eq!(monitor.blocks_run(), 3);
+
+    // only the latest miss is kept
+    let misses = Vec::from_iter(monitor.misses());
+    assert_eq!(misses.len(), 1);
+    assert_eq!(misses[0].block, 2);
+    assert_eq!(misses[0].task, slow_task);
+    assert!(misses[0].elapsed > misses[0].deadline);
+
+    // deadlines too long to be represented are never missed
+    let mut monitor = DeadlineMonitor::new(1, f64::MIN_POSITIVE);
+    assert!(monitor.run_block(&schedule, &mut runner, usize::MAX));
+}
+
+#[test]
+fn run_multiple_adders() {
+    let mut graph = AudioGraph::default();
+
+    let mut master = Node::default();
+    let master_input = master.add_input();
+    let master_id = graph.insert_node(master);
+
+    let nodes: [_; 3] = array::from_fn(|_i| {
+        let mut node = Node::default();
+        (node.add_output(), graph.insert_node(node))
+    });
+
+    for (node_output, node_id) in &nodes {
+        assert!(graph
+            .try_insert_edge(
+                (node_id.clone(), node_output.clone()),
+                (master_id.clone(), master_input.clone())
+            )
+            .is_ok_and(id));
+    }
+
+    let (num_buffers, schedule) = graph.compile([master_id.clone()]);
+
+    let mut runner = SummingRunner {
+        buffers: vec![0; num_buffers],
+        ..Default::default()
+    };
+
+    run_schedule(&schedule, &mut runner);
+
+    assert_eq!(
+        runner.received[&master_id],
+        nodes.iter().map(|(_, id)| 1 << id.0).sum()
+    );
+}
+
+#[test]
+fn canonicalize_schedule() {
+    let mut schedule = [
+        Task::node(NodeID(1), [], [(OutputID(0), 3)]),
+        Task::node(NodeID(2), [], [(OutputID(0), 1)]),
+        Task::sum(1, 3, 1),
+        Task::node(NodeID(0), [(InputID(1), 1), (InputID(0), 3)], []),
+    ];
+
+    canonicalize(&mut schedule);
+
+    assert_eq!(
+        schedule,
+        [
+            Task::node(NodeID(1), [], [(OutputID(0), 0)]),
+            Task::node(NodeID(2), [], [(OutputID(0), 1)]),
+            Task::sum(1, 0, 1),
+            Task::node(NodeID(0), [(InputID(1), 1), (InputID(0), 0)], []),
+        ]
+    );
+}
+
+#[test]
+fn inequivalent_schedules() {
+    let schedule = [
+        Task::node(NodeID(1), [], [(OutputID(0), 0)]),
+        Task::node(NodeID(2), [], [(OutputID(0), 1)]),
+        Task::sum(1, 0, 0),
+        Task::node(NodeID(0), [(InputID(0), 0)], []),
+    ];
+
+    let mut reordered = schedule.clone();
+    reordered.swap(0, 1);
+    assert!(schedules_equivalent(&schedule, &reordered));
+
+    let mut missing_sum = schedule.clone();
+    missing_sum[2] = Task::sum(1, 1, 0);
+    assert!(!schedules_equivalent(&schedule, &missing_sum));
+}
+
+#[test]
+fn critical_path_and_slack() {
+    graph! {
+        graph = {
+            source @ 10: [] => [source_out];
+            fast @ 5: [fast_in] => [fast_out];
+            slow @ 20: [slow_in] => [slow_out];
+            passthrough: [passthrough_in] => [passthrough_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> fast.fast_in;
+        source.source_out -> passthrough.passthrough_in;
+        passthrough.passthrough_out -> slow.slow_in;
+        fast.fast_out -> master.master_in;
+        slow.slow_out -> master.master_in;
+    }
+
+    let (latency, path) = graph.critical_path([master.clone()]);
+
+    assert_eq!(latency, 30);
+    assert_eq!(
+        path,
+        [
+            source.clone(),
+            passthrough.clone(),
+            slow.clone(),
+            master.clone()
+        ]
+    );
+
+    let slack = graph.latency_slack([master.clone()]);
+
+    assert_eq!(
+        slack,
+        FnvHashMap::from_iter([
+            (source.clone(), 0),
+            (fast.clone(), 15),
+            (passthrough, 0),
+            (slow.clone(), 0),
+            (master.clone(), 0),
+        ])
+    );
+
+    // latencies saturate instead of overflowing
+    graph.set_latency(&slow, u64::MAX);
+
+    assert_eq!(graph.critical_path([master.clone()]).0, u64::MAX);
+
+    let slack = graph.latency_slack([master]);
+    assert_eq!(slack[&source], 0);
+    assert_eq!(slack[&fast], u64::MAX - 15);
+    assert_eq!(slack[&slow], 0);
+}
+
+#[test]
+fn bus_edges() {
+    let mut graph = AudioGraph::default();
+
+    let mut source = Node::default();
+    let source_bus = source.add_output_bus(2);
+    let source_id = graph.insert_node(source);
+
+    let mut sink = Node::default();
+    let sink_bus = sink.add_input_bus(2);
+    let sink_narrow_bus = sink.add_input_bus(1);
+    let sink_id = graph.insert_node(sink);
+
+    assert!(graph
+        .try_insert_bus_edge(
+            (source_id.clone(), &source_bus),
+            (sink_id.clone(), &sink_narrow_bus),
+        )
+        .is_err_and(Not::not));
+
+    // nothing must have been connected
+    assert!(graph[&sink_id]
+        .inputs()
+        .values()
+        .all(|input| input.connections().is_empty()));
+
+    assert!(graph
+        .try_insert_bus_edge(
+            (source_id.clone(), &source_bus),
+            (sink_id.clone(), &sink_bus),
+        )
+        .is_ok_and(id));
+    assert!(graph
+        .try_insert_bus_edge(
+            (source_id.clone(), &source_bus),
+            (sink_id.clone(), &sink_bus),
+        )
+        .is_ok_and(Not::not));
+
+    for (output, input) in zip(source_bus, sink_bus) {
+        assert_eq!(
+            graph[&sink_id].inputs()[&input].connections(),
+            &FnvHashMap::from_iter([(source_id.clone(), FnvHashSet::from_iter([output]))])
+        );
+    }
+}
+
+#[test]
+fn schedule_cost() {
+    let schedule = [
+        Task::node(NodeID(1), [], [(OutputID(0), 0)]),
+        Task::node(NodeID(2), [], [(OutputID(0), 1)]),
+        Task::sum(1, 0, 0),
+        Task::node(NodeID(0), [(InputID(0), 0)], []),
+    ];
+
+    let cost = ScheduleCost::new(
+        &schedule,
+        |id| Cost::new(id.0 as f64, 1.),
+        Cost::new(0., 0.5),
+    );
+
+    assert_eq!(cost.nodes, Cost::new(3., 3.));
+    assert_eq!(cost.sums, Cost::new(0., 0.5));
+    assert_eq!(cost.block_cost(4), 17.);
+
+    assert_eq!(cost.min_block_size(4.), Some(6));
+    assert_eq!(cost.min_block_size(3.5), None);
+}
+
+#[test]
+fn schedule_queries() {
+    graph! {
+        graph = {
+            left: [] => [left_out];
+            right: [] => [right_out];
+            master: [master_in] => [];
+        }
+        left.left_out -> master.master_in;
+        right.right_out -> master.master_in;
+    }
+
+    let (_, schedule) = graph.compile([master.clone()]);
+
+    let master_buf = input_buffer_of(&schedule, &master, &master_in).unwrap();
+    let left_buf = output_buffer_of(&schedule, &left, &left_out).unwrap();
+    let right_buf = output_buffer_of(&schedule, &right, &right_out).unwrap();
+
+    assert!(master_buf == left_buf || master_buf == right_buf);
+    assert_eq!(input_buffer_of(&schedule, &master, &InputID(1)), None);
+    assert_eq!(output_buffer_of(&schedule, &master, &OutputID(0)), None);
+
+    assert_eq!(
+        source_chain(&schedule, &master, &master_in),
+        Some(vec![(left, left_out), (right, right_out)])
+    );
+}
+
+#[test]
+fn node_user_data() {
+    let mut graph = AudioGraph::default();
+
+    let mut node = Node::default();
+    node.add_output();
+    assert!(node.set_user_data("filter").is_none());
+
+    let node_id = graph.insert_node(node);
+    assert_eq!(graph[&node_id].user_data::<&str>(), Some(&"filter"));
+    assert_eq!(graph[&node_id].user_data::<u32>(), None);
+
+    let node = graph.get_node_mut(&node_id).unwrap();
+    let old = node.set_user_data(42u32).unwrap();
+    assert_eq!(old.downcast_ref::<&str>(), Some(&"filter"));
+    assert_eq!(graph[&node_id].clone().user_data::<u32>(), Some(&42));
+}
+
+#[test]
+fn graph_capacity() {
+    let mut graph = AudioGraph::with_capacity(4);
+    assert!(graph.nodes.capacity() >= 4);
+    graph.reserve_nodes(16);
+    assert!(graph.nodes.capacity() >= 16);
+
+    let mut node = Node::default();
+    node.reserve_ports(16, 16);
+    assert!(node.inputs.capacity() >= 16 && node.output_ids.capacity() >= 16);
+    let input = node.add_input();
+    node.add_output();
+
+    let node_id = graph.insert_node(node);
+    assert!(graph.reserve_edges((&node_id, &input), 16));
+    assert!(!graph.reserve_edges((&node_id, &InputID(u32::MAX)), 16));
+    assert!(graph[&node_id].inputs()[&input].connections().capacity() >= 16);
+
+    // nodes shared with a clone of the graph are neither copied, nor shrunk
+    let shared = graph.clone();
+    graph.shrink_to_fit();
+
+    assert!(graph.nodes.capacity() < 16);
+    assert!(Arc::ptr_eq(&graph.nodes[&node_id], &shared.nodes[&node_id]));
+    assert!(graph[&node_id].inputs.capacity() >= 16);
+
+    drop(shared);
+    graph.shrink_to_fit();
+
+    let node = &graph[&node_id];
+    assert_eq!(node.inputs().len(), 1);
+    assert_eq!(node.output_ids().len(), 1);
+    assert!(node.inputs.capacity() < 16 && node.output_ids.capacity() < 16);
+    assert!(node.inputs()[&input].connections().capacity() < 16);
+}
+
+#[test]
+fn compile_into_reuses_storage() {
+    let test = testing::dense_mixer(16, 4);
+
+    let ((num_buffers, schedule), compile_calls) = count_allocations(|| test.compile());
+
+    let mut reused = schedule.clone();
+    reused.push(Task::sum(5, 6, 7));
+
+    let (reused_num_buffers, reuse_calls) = count_allocations(|| {
+        test.graph
+            .compile_into(test.roots.iter().cloned(), &mut reused)
+    });
+
+    assert_eq!(reused_num_buffers, num_buffers);
+    assert_eq!(reused, schedule);
+
+    // every non-empty port map, and the task list, are allocated when compiling into
+    // an empty schedule, but not when compiling into one of the same shape
+    let port_maps = schedule
+        .iter()
+        .map(|task| match task {
+            Task::Node {
+                inputs, outputs, ..
+            } => usize::from(!inputs.is_empty()) + usize::from(!outputs.is_empty()),
+            Task::Sum { .. } => 0,
+        })
+        .sum::<usize>();
+
+    assert!(
+        reuse_calls + port_maps < compile_calls,
+        "{reuse_calls} + {port_maps} >= {compile_calls}"
+    );
+}
+
+#[test]
+fn graph_change_log() {
+    let mut graph = AudioGraph::default();
+
+    let mut source = Node::default();
+    let source_output = source.add_output();
+    let source_id = graph.insert_node(source);
+
+    // not recording yet
+    graph.record_changes(true);
+    assert!(graph.is_recording_changes());
+
+    let mut sink = Node::default();
+    let sink_input = sink.add_input();
+    let sink_id = graph.insert_node(sink);
+
+    let from = (source_id.clone(), source_output);
+    let to = (sink_id.clone(), sink_input);
+
+    assert!(graph
+        .try_insert_edge(from.clone(), to.clone())
+        .is_ok_and(id));
+    assert!(graph
+        .try_insert_edge(from.clone(), to.clone())
+        .is_ok_and(Not::not));
+    assert_eq!(graph.set_latency(&source_id, 32), Some(0));
+    assert_eq!(graph.set_latency(&source_id, 32), Some(32));
+
+    assert_eq!(
+        graph.drain_changes().collect::<Vec<_>>(),
+        [
+            GraphChange::NodeInserted(sink_id.clone()),
+            GraphChange::EdgeInserted {
+                from: from.clone(),
+                to: to.clone()
+            },
+            GraphChange::LatencyChanged {
+                node: source_id.clone(),
+                old: 0,
+                new: 32
+            },
+        ]
+    );
+
+    assert_eq!(graph.drain_changes().count(), 0);
+
+    let removed = graph.remove_node(&source_id).unwrap();
+    assert_eq!(removed.latency, 32);
+    assert!(graph[&sink_id].inputs()[&to.1].connections().is_empty());
+    assert!(!graph.remove_edge(from.clone(), to.clone()));
+
+    assert_eq!(
+        graph.drain_changes().collect::<Vec<_>>(),
+        [
+            GraphChange::EdgeRemoved { from, to },
+            GraphChange::NodeRemoved(source_id),
+        ]
+    );
+
+    // dropping the iterator without consuming it removes the changes too
+    graph.insert_node(Node::default());
+    let _ = graph.drain_changes();
+    assert_eq!(graph.drain_changes().count(), 0);
+
+    graph.record_changes(false);
+    graph.insert_node(Node::default());
+    assert_eq!(graph.drain_changes().count(), 0);
+}
+
+#[test]
+fn unconnected_inputs() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            master: [master_in, unconnected] => [];
+        }
+        source.source_out -> master.master_in;
+    }
+
+    let node = graph.get_node_mut(&master).unwrap();
+    assert_eq!(node.input_default(&unconnected), Some(0.));
+    assert_eq!(node.set_input_default(&unconnected, 1.), Some(0.));
+    assert_eq!(node.input_default(&unconnected), Some(1.));
+    assert_eq!(node.input_default(&InputID(2)), None);
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+
+    assert_eq!(
+        schedule,
+        [
+            Task::node(source, [], [(source_out, 0)]),
+            Task::node(master, [(master_in, 0)], []),
         ]
     );
 
     assert_eq!(num_buffers, 1);
 }
+
+#[test]
+fn grouped_compilation() {
+    graph! {
+        graph = {
+            osc1: [] => [osc1_out];
+            filter1: [filter1_in] => [filter1_out];
+            osc2: [] => [osc2_out];
+            filter2: [filter2_in] => [filter2_out];
+            master: [master_in] => [];
+        }
+        osc1.osc1_out -> filter1.filter1_in;
+        osc2.osc2_out -> filter2.filter2_in;
+        filter1.filter1_out -> master.master_in;
+        filter2.filter2_out -> master.master_in;
+    }
+
+    let types = FnvHashMap::from_iter([
+        (osc1, "osc"),
+        (osc2, "osc"),
+        (filter1, "filter"),
+        (filter2, "filter"),
+        (master.clone(), "master"),
+    ]);
+
+    let mut key_calls = 0;
+    let (num_buffers, schedule) = graph.compile_grouped([master.clone()], |id, _| {
+        key_calls += 1;
+        types[id]
+    });
+
+    // once per node
+    assert_eq!(key_calls, 5);
+
+    let node_types = Vec::from_iter(schedule.iter().filter_map(|task| match task {
+        Task::Node { id, .. } => Some(types[id]),
+        Task::Sum { .. } => None,
+    }));
+
+    assert_eq!(node_types, ["osc", "osc", "filter", "filter", "master"]);
+    assert_eq!(num_buffers, 2);
+
+    assert_schedule_equiv(&schedule, &graph.compile([master]).1);
+}
+
+#[test]
+fn ordering_heuristics() {
+    graph! {
+        graph = {
+            osc1: [] => [osc1_out];
+            filter1: [filter1_in] => [filter1_out];
+            osc2: [] => [osc2_out];
+            filter2: [filter2_in] => [filter2_out];
+            master: [master_in] => [];
+        }
+        osc1.osc1_out -> filter1.filter1_in;
+        osc2.osc2_out -> filter2.filter2_in;
+        filter1.filter1_out -> master.master_in;
+        filter2.filter2_out -> master.master_in;
+    }
+
+    let node_order = |schedule: &[Task]| {
+        Vec::from_iter(schedule.iter().filter_map(|task| match task {
+            Task::Node { id, .. } => Some(id.clone()),
+            Task::Sum { .. } => None,
+        }))
+    };
+
+    let (_, schedule) = graph.compile_with(
+        [master.clone()],
+        CompileOptions::new().ordering(InsertionOrder),
+    );
+
+    assert_eq!(
+        node_order(&schedule),
+        [
+            osc1.clone(),
+            filter1.clone(),
+            osc2.clone(),
+            filter2.clone(),
+            master.clone()
+        ]
+    );
+    assert_schedule_equiv(&schedule, &graph.compile([master.clone()]).1);
+
+    let costs = FnvHashMap::from_iter([
+        (osc1.clone(), 2.),
+        (osc2.clone(), 3.),
+        (filter1.clone(), 5.),
+        (filter2.clone(), 1.),
+        (master.clone(), 1.),
+    ]);
+
+    let (_, schedule) = graph.compile_with(
+        [master.clone()],
+        CompileOptions::new().ordering(ByCost::new(64, |id, _| Cost::new(costs[id], 0.))),
+    );
+
+    assert_eq!(
+        node_order(&schedule),
+        [osc2, osc1, filter1, filter2, master.clone()]
+    );
+    assert_schedule_equiv(&schedule, &graph.compile([master]).1);
+}
+
+#[test]
+fn minimize_live_buffers() {
+    graph! {
+        graph = {
+            gen_a: [] => [a1, a2];
+            sink_a: [sink_a1, sink_a2] => [];
+            gen_b: [] => [b1, b2];
+            sink_b: [sink_b1, sink_b2] => [];
+        }
+        gen_a.a1 -> sink_a.sink_a1;
+        gen_a.a2 -> sink_a.sink_a2;
+        gen_b.b1 -> sink_b.sink_b1;
+        gen_b.b2 -> sink_b.sink_b2;
+    }
+
+    let roots = [gen_a, gen_b, sink_a, sink_b];
+
+    let (num_buffers, schedule) = graph.compile_with(
+        roots.clone(),
+        CompileOptions::new().ordering(MinimizeLiveBuffers),
+    );
+
+    // each generator's outputs are consumed before the other one runs
+    assert_eq!(num_buffers, 2);
+    assert_eq!(
+        validate_schedule(&graph, roots, num_buffers, &schedule),
+        Ok(())
+    );
+}
+
+#[test]
+fn graph_snapshots() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> master.master_in;
+    }
+
+    let snapshot = graph.snapshot();
+
+    assert!(graph.remove_edge(
+        (source.clone(), source_out.clone()),
+        (master.clone(), master_in.clone())
+    ));
+    assert_eq!(graph.set_latency(&source, 64), Some(0));
+    assert_eq!(snapshot[&source].latency, 0);
+
+    let handle = std::thread::spawn({
+        let master = master.clone();
+        move || snapshot.compile([master])
+    });
+
+    let (num_buffers, schedule) = handle.join().unwrap();
+
+    assert_eq!(num_buffers, 1);
+    assert_eq!(
+        schedule,
+        [
+            Task::node(source.clone(), [], [(source_out, 0)]),
+            Task::node(master.clone(), [(master_in, 0)], []),
+        ]
+    );
+
+    assert_eq!(graph[&source].latency, 64);
+    assert_eq!(graph.compile([source, master]).1.len(), 2);
+}
+
+#[test]
+fn compile_service() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            master: [master_in] => [];
+        }
+    }
+
+    let mut service = CompileService::new();
+
+    let first = service.request(graph.snapshot(), [master.clone()]).unwrap();
+
+    assert!(graph
+        .try_insert_edge(
+            (source.clone(), source_out.clone()),
+            (master.clone(), master_in.clone())
+        )
+        .is_ok_and(id));
+
+    let second = service.request(graph.snapshot(), [master.clone()]).unwrap();
+    assert!(first < second);
+
+    // the first request may, or may not, have been delivered before being superseded
+    let mut result = service.recv().unwrap();
+    if result.request == first {
+        result = service.recv().unwrap();
+    }
+
+    assert_eq!(
+        result,
+        CompiledSchedule {
+            request: second,
+            num_buffers: 1,
+            schedule: graph.compile([master]).1,
+        }
+    );
+
+    assert!(service.try_recv().is_none());
+}
+
+#[test]
+fn graph_editor() {
+    graph! {
+        graph = {
+            master: [master_in] => [];
+        }
+    }
+
+    let (mut editor, outcomes) = GraphEditor::new(graph, [master.clone()]);
+
+    assert_eq!(editor.apply_pending(), Ok(None));
+
+    let mut source = Node::default();
+    let source_out = source.add_output();
+
+    let sender = editor.command_sender();
+    std::thread::spawn(move || sender.send(GraphEdit::InsertNode(source)).unwrap())
+        .join()
+        .unwrap();
+
+    let request = editor.apply_pending().unwrap().unwrap();
+
+    let Ok(EditOutcome::NodeInserted(source)) = outcomes.recv() else {
+        panic!("expected a node insertion")
+    };
+
+    let sender = editor.command_sender();
+
+    for edit in [
+        GraphEdit::InsertEdge {
+            from: (source.clone(), source_out.clone()),
+            to: (master.clone(), master_in.clone()),
+        },
+        GraphEdit::SetLatency {
+            node: source.clone(),
+            latency: 16,
+        },
+        GraphEdit::RemoveNode(NodeID(42)),
+    ] {
+        sender.send(edit).unwrap();
+    }
+
+    let next_request = editor.apply_pending().unwrap().unwrap();
+    assert!(request < next_request);
+
+    assert!(matches!(
+        outcomes.try_recv(),
+        Ok(EditOutcome::EdgeInserted(Ok(true)))
+    ));
+    assert!(matches!(
+        outcomes.try_recv(),
+        Ok(EditOutcome::LatencySet(Some(0)))
+    ));
+    assert!(matches!(
+        outcomes.try_recv(),
+        Ok(EditOutcome::NodeRemoved(None))
+    ));
+
+    let mut result = editor.compiler().recv().unwrap();
+    if result.request == request {
+        result = editor.compiler().recv().unwrap();
+    }
+
+    assert_eq!(result.request, next_request);
+    assert_eq!(
+        result.schedule,
+        [
+            Task::node(source, [], [(source_out, 0)]),
+            Task::node(master, [(master_in, 0)], []),
+        ]
+    );
+}
+
+#[test]
+fn undo_redo() {
+    let mut graph = AudioGraph::default();
+    let mut history = EditHistory::new(8);
+
+    let mut source = Node::default();
+    let source_out = source.add_output();
+
+    let mut master = Node::default();
+    let master_in = master.add_input();
+
+    history.begin_transaction();
+
+    let EditOutcome::NodeInserted(source) =
+        history.apply(&mut graph, GraphEdit::InsertNode(source))
+    else {
+        panic!()
+    };
+    let EditOutcome::NodeInserted(master) =
+        history.apply(&mut graph, GraphEdit::InsertNode(master))
+    else {
+        panic!()
+    };
+
+    let edge = (
+        (source.clone(), source_out.clone()),
+        (master.clone(), master_in.clone()),
+    );
+
+    history.apply(
+        &mut graph,
+        GraphEdit::InsertEdge {
+            from: edge.0.clone(),
+            to: edge.1.clone(),
+        },
+    );
+
+    history.commit_transaction();
+
+    // merged into one undo step
+    for latency in [8, 16, 32] {
+        history.apply(
+            &mut graph,
+            GraphEdit::SetLatency {
+                node: source.clone(),
+                latency,
+            },
+        );
+    }
+
+    history.apply(&mut graph, GraphEdit::RemoveNode(master.clone()));
+    assert!(graph.get_node(&master).is_none());
+
+    let (_, schedule) = graph.compile([source.clone()]);
+
+    assert!(history.undo(&mut graph));
+    assert_eq!(
+        graph[&master].inputs()[&master_in].connections(),
+        &FnvHashMap::from_iter([(source.clone(), FnvHashSet::from_iter([source_out]))])
+    );
+
+    assert!(history.undo(&mut graph));
+    assert_eq!(graph[&source].latency, 0);
+
+    assert!(history.undo(&mut graph));
+    assert!(graph.get_node(&source).is_none() && graph.get_node(&master).is_none());
+    assert!(!history.undo(&mut graph));
+
+    assert!(history.redo(&mut graph));
+    assert!(history.redo(&mut graph));
+    assert_eq!(graph[&source].latency, 32);
+    assert!(history.redo(&mut graph));
+    assert!(!history.redo(&mut graph));
+
+    assert_eq!(graph.compile([source]).1, schedule);
+}
+
+#[test]
+fn history_depth() {
+    let mut graph = AudioGraph::default();
+    let mut history = EditHistory::new(2);
+
+    for _ in 0..3 {
+        history.apply(&mut graph, GraphEdit::InsertNode(Node::default()));
+    }
+
+    assert!(history.undo(&mut graph));
+    assert!(history.undo(&mut graph));
+    assert!(!history.undo(&mut graph));
+    assert!(graph.get_node(&NodeID(0)).is_some());
+}
+
+#[test]
+fn pretty_print_schedule() {
+    let schedule = [
+        Task::node(NodeID(1), [], [(OutputID(1), 2), (OutputID(0), 0)]),
+        Task::node(NodeID(2), [], [(OutputID(0), 1)]),
+        Task::sum(1, 0, 0),
+        Task::node(NodeID(0), [(InputID(1), 2), (InputID(0), 0)], []),
+    ];
+
+    assert_eq!(
+        pretty_schedule(&schedule),
+        "\
+0: node 1 | inputs: | outputs: 0 -> #0, 1 -> #2
+1: node 2 | inputs: | outputs: 0 -> #1
+2: sum #1 + #0 -> #0
+3: node 0 | inputs: 0 <- #0, 1 <- #2 | outputs:
+"
+    );
+}
+
+#[test]
+fn validate_schedules() {
+    graph! {
+        graph = {
+            left: [] => [left_out];
+            right: [] => [right_out];
+            master: [master_in] => [];
+        }
+        left.left_out -> master.master_in;
+        right.right_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+    assert_eq!(
+        validate_schedule(&graph, [master.clone()], num_buffers, &schedule),
+        Ok(())
+    );
+
+    assert_eq!(
+        validate_schedule(&graph, [master.clone()], 1, &schedule),
+        Err(ScheduleError::BufferOutOfRange { task: 1, buffer: 1 })
+    );
+
+    // master before it's inputs are summed
+    let mut reordered = schedule.clone();
+    let master_task = reordered.pop().unwrap();
+    reordered.insert(2, master_task);
+    assert!(matches!(
+        validate_schedule(&graph, [master.clone()], num_buffers, &reordered),
+        Err(ScheduleError::WrongInput { node, .. }) if node == master,
+    ));
+
+    assert!(matches!(
+        validate_schedule(&graph, [master.clone()], num_buffers, &schedule[2..]),
+        Err(ScheduleError::MissingNode(node)) if node != master,
+    ));
+
+    assert_eq!(
+        validate_schedule(&graph, [left.clone()], num_buffers, &schedule),
+        Err(ScheduleError::UnexpectedNode(right)),
+    );
+}
+
+#[test]
+fn dependent_root_nodes() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            sink: [sink_in] => [];
+        }
+        source.source_out -> sink.sink_in;
+    }
+
+    for roots in [
+        [source.clone(), sink.clone()],
+        [sink.clone(), source.clone()],
+    ] {
+        let (num_buffers, schedule) = graph.compile(roots.clone());
+        assert_eq!(
+            validate_schedule(&graph, roots, num_buffers, &schedule),
+            Ok(())
+        );
+    }
+}
+
+#[test]
+fn fuzz_graph_ops() {
+    // xorshift
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next_byte = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as u8
+    };
+
+    for _ in 0..500 {
+        let data: [u8; 512] = array::from_fn(|_| next_byte());
+        fuzz::graph_ops(&data);
+    }
+}
+
+#[test]
+fn partially_connected_outputs() {
+    graph! {
+        graph = {
+            source: [] => [_unconnected1, source_out, _unconnected2];
+            master: [master_in] => [];
+        }
+        source.source_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+
+    assert_eq!(
+        schedule,
+        [
+            Task::node(source, [], [(source_out, 0)]),
+            Task::node(master, [(master_in, 0)], []),
+        ]
+    );
+
+    assert_eq!(num_buffers, 1);
+}
+
+#[test]
+fn multiple_connected_outputs() {
+    graph! {
+        graph = {
+            source: [] => [out_1, out_2, out_3];
+            sink: [in_1, in_2] => [];
+        }
+        source.out_1 -> sink.in_1;
+        source.out_2 -> sink.in_1;
+        source.out_3 -> sink.in_2;
+    }
+
+    let (num_buffers, schedule) = graph.compile([sink.clone()]);
+
+    assert_eq!(
+        validate_schedule(&graph, [sink], num_buffers, &schedule),
+        Ok(())
+    );
+
+    assert_eq!(num_buffers, 3);
+}
+
+#[test]
+fn generated_graphs() {
+    use testing::*;
+
+    let random = RandomDag {
+        nodes: 64,
+        latency: 0..16,
+        ..Default::default()
+    };
+
+    for (test, num_nodes) in [
+        (chain(5, 16), 5),
+        (diamond(4), 6),
+        (m_shape(3), 7),
+        (w_shape(3), 7),
+        (dense_mixer(4, 2), 5),
+        (random_dag(&random), 64),
+    ] {
+        assert_eq!(test.graph.nodes().count(), num_nodes);
+
+        let (num_buffers, schedule) = test.compile();
+        assert_eq!(
+            validate_schedule(&test.graph, test.roots.clone(), num_buffers, &schedule),
+            Ok(())
+        );
+    }
+
+    assert_eq!(chain(5, 16).graph.critical_path(chain(5, 16).roots).0, 80);
+
+    // generation is deterministic
+    assert_schedule_equiv(
+        &random_dag(&random).compile().1,
+        &random_dag(&random).compile().1,
+    );
+}
+
+#[test]
+fn latency_units() {
+    assert_eq!(Latency::Samples(64).to_samples(48000., 120.), 64);
+    assert_eq!(Latency::Seconds(0.01).to_samples(48000., 120.), 480);
+    // a quarter note lasts half a second at 120 bpm
+    assert_eq!(Latency::Beats(0.25).to_samples(48000., 120.), 6000);
+    assert_eq!(Latency::Seconds(-1.).to_samples(48000., 120.), 0);
+
+    graph! {
+        graph = {
+            lookahead @ 7: [_lookahead_in] => [lookahead_out];
+            delay: [delay_in] => [];
+        }
+        lookahead.lookahead_out -> delay.delay_in;
+    }
+
+    graph
+        .get_node_mut(&lookahead)
+        .unwrap()
+        .set_unresolved_latency(Latency::Seconds(0.005));
+    graph
+        .get_node_mut(&delay)
+        .unwrap()
+        .set_unresolved_latency(Latency::Beats(1.));
+
+    graph.resolve_latencies(48000., 120.);
+    assert_eq!(graph[&lookahead].latency, 240);
+    assert_eq!(graph[&delay].latency, 24000);
+
+    graph.resolve_latencies(44100., 60.);
+    assert_eq!(graph[&lookahead].latency, 221);
+    assert_eq!(graph[&delay].latency, 44100);
+}
+
+#[test]
+fn remap_buffers_across_recompiles() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            filter: [filter_in] => [filter_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> filter.filter_in;
+        filter.filter_out -> master.master_in;
+    }
+
+    let (_, previous) = graph.compile([master.clone()]);
+
+    let mut osc = Node::default();
+    let osc_out = osc.add_output();
+    let osc = graph.insert_node(osc);
+
+    assert!(graph
+        .try_insert_edge((osc, osc_out), (master.clone(), master_in.clone()))
+        .is_ok_and(id));
+
+    let (num_buffers, mut schedule) = graph.compile([master.clone()]);
+    let unmapped = schedule.clone();
+
+    remap_buffers(&previous, &mut schedule, num_buffers);
+
+    assert_schedule_equiv(&schedule, &unmapped);
+    assert_eq!(
+        validate_schedule(&graph, [master.clone()], num_buffers, &schedule),
+        Ok(())
+    );
+
+    for (node, output) in [(&source, &source_out), (&filter, &filter_out)] {
+        assert_eq!(
+            output_buffer_of(&schedule, node, output),
+            output_buffer_of(&previous, node, output),
+        );
+    }
+
+    assert_eq!(
+        input_buffer_of(&schedule, &filter, &filter_in),
+        input_buffer_of(&previous, &filter, &filter_in),
+    );
+
+    // remapping a schedule against itself changes nothing
+    let mut same = previous.clone();
+    remap_buffers(&previous, &mut same, 1);
+    assert_eq!(same, previous);
+}
+
+#[test]
+fn sum_buffer_policies() {
+    use testing::*;
+
+    let aliases = |schedule: &[Task]| {
+        schedule.iter().any(|task| {
+            matches!(task, Task::Sum { left, right, output } if output == left || output == right)
+        })
+    };
+
+    let mut any_aliased = false;
+
+    for test in [
+        diamond(4),
+        w_shape(3),
+        dense_mixer(4, 2),
+        random_dag(&RandomDag::default()),
+    ] {
+        let (_, default) = test.compile();
+        any_aliased |= aliases(&default);
+
+        let (num_buffers, schedule) = test.graph.compile_with(
+            test.roots.clone(),
+            CompileOptions::new().sum_buffers(SumBufferPolicy::AlwaysNew),
+        );
+
+        assert!(!aliases(&schedule));
+        assert_eq!(
+            validate_schedule(&test.graph, test.roots.clone(), num_buffers, &schedule),
+            Ok(())
+        );
+    }
+
+    assert!(any_aliased);
+}
+
+#[test]
+fn max_block_sizes() {
+    let mut node = Node::default();
+    assert_eq!(node.set_max_block_size(Some(64)), None);
+    assert_eq!(node.max_block_size(), Some(64));
+
+    assert_eq!(
+        Vec::from_iter(split_block(150, node.max_block_size())),
+        [0..64, 64..128, 128..150]
+    );
+    assert_eq!(Vec::from_iter(split_block(128, Some(64))), [0..64, 64..128]);
+    assert!(split_block(32, Some(64)).eq(iter::once(0..32)));
+    assert!(split_block(32, None).eq(iter::once(0..32)));
+    assert_eq!(split_block(0, None).count(), 0);
+
+    assert_eq!(node.set_max_block_size(None), Some(64));
+}
+
+#[test]
+fn task_graphs() {
+    use testing::*;
+
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> master.master_in;
+    }
+
+    let chain = task_graph(&graph.compile([master]).1);
+    assert_eq!(chain.len(), 2);
+    assert_eq!(Vec::from_iter(chain.edges()), [(0, 1)]);
+    assert!(chain.is_topological_order(&[0, 1]));
+    assert!(!chain.is_topological_order(&[1, 0]));
+    assert!(!chain.is_topological_order(&[0, 0]));
+
+    for test in [
+        diamond(4),
+        m_shape(3),
+        dense_mixer(3, 2),
+        random_dag(&RandomDag::default()),
+    ] {
+        let (num_buffers, schedule) = test.compile();
+        let task_graph = task_graph(&schedule);
+
+        let serial = Vec::from_iter(0..schedule.len());
+        assert!(task_graph.is_topological_order(&serial));
+
+        // any other topological order must process the graph just as well,
+        // here, the one always running the latest ready task first
+        let mut in_degrees =
+            Vec::from_iter((0..schedule.len()).map(|i| task_graph.dependencies(i).len()));
+        let mut order = vec![];
+
+        while let Some(task) = (0..schedule.len()).rev().find(|&i| in_degrees[i] == 0) {
+            in_degrees[task] = usize::MAX;
+            order.push(task);
+
+            for (dep, dependent) in task_graph.edges() {
+                if dep == task {
+                    in_degrees[dependent] -= 1;
+                }
+            }
+        }
+
+        assert!(task_graph.is_topological_order(&order));
+
+        let reordered = Vec::from_iter(order.iter().map(|&i| schedule[i].clone()));
+        assert_eq!(
+            validate_schedule(&test.graph, test.roots.clone(), num_buffers, &reordered),
+            Ok(())
+        );
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_round_trip() {
+    use core::{ffi::c_void, ptr};
+    use ffi::*;
+
+    #[derive(Default)]
+    struct Calls {
+        nodes: Vec<(u32, Vec<PolygraphPortBuffer>, Vec<PolygraphPortBuffer>)>,
+        sums: usize,
+    }
+
+    extern "C" fn run_node(
+        user_data: *mut c_void,
+        node: u32,
+        inputs: *const PolygraphPortBuffer,
+        num_inputs: usize,
+        outputs: *const PolygraphPortBuffer,
+        num_outputs: usize,
+    ) {
+        let calls = unsafe { &mut *user_data.cast::<Calls>() };
+        let ports = |ptr, len| unsafe { core::slice::from_raw_parts(ptr, len).to_vec() };
+        calls
+            .nodes
+            .push((node, ports(inputs, num_inputs), ports(outputs, num_outputs)));
+    }
+
+    extern "C" fn run_sum(user_data: *mut c_void, _left: usize, _right: usize, _output: usize) {
+        unsafe { &mut *user_data.cast::<Calls>() }.sums += 1;
+    }
+
+    unsafe {
+        let graph = polygraph_graph_new();
+
+        let source1 = polygraph_graph_add_node(graph, 0, 1, 0);
+        let source2 = polygraph_graph_add_node(graph, 0, 1, 0);
+        let master = polygraph_graph_add_node(graph, 1, 0, 0);
+
+        assert_eq!(polygraph_graph_add_edge(graph, source1, 0, master, 0), 1);
+        assert_eq!(polygraph_graph_add_edge(graph, source1, 0, master, 0), 0);
+        assert_eq!(polygraph_graph_add_edge(graph, source2, 0, master, 0), 1);
+        assert_eq!(polygraph_graph_add_edge(graph, master, 0, source1, 0), -2);
+        assert_eq!(polygraph_graph_add_edge(graph, source1, 3, master, 0), -2);
+
+        assert!(polygraph_graph_compile(graph, [42].as_ptr(), 1).is_null());
+
+        let schedule = polygraph_graph_compile(graph, [master].as_ptr(), 1);
+        assert!(!schedule.is_null());
+        assert_eq!(polygraph_schedule_num_buffers(schedule), 2);
+
+        let mut calls = Calls::default();
+        let runner = PolygraphRunner {
+            user_data: ptr::from_mut(&mut calls).cast(),
+            run_node,
+            run_sum,
+        };
+        polygraph_schedule_run(schedule, &runner);
+
+        assert_eq!(calls.nodes.len(), 3);
+        assert_eq!(calls.sums, 1);
+
+        let (id, inputs, outputs) = calls.nodes.last().unwrap();
+        assert_eq!(*id, master);
+        assert_eq!(inputs.len(), 1);
+        assert!(outputs.is_empty());
+
+        assert!(polygraph_graph_remove_edge(graph, source2, 0, master, 0));
+        assert!(!polygraph_graph_remove_edge(graph, source2, 0, master, 0));
+        assert!(polygraph_graph_remove_node(graph, source2));
+        assert!(!polygraph_graph_remove_node(graph, source2));
+
+        polygraph_schedule_free(schedule);
+        polygraph_graph_free(graph);
+    }
+}
+
+#[test]
+fn compact_schedules() {
+    use testing::*;
+
+    for test in [
+        chain(4, 0),
+        diamond(3),
+        dense_mixer(3, 4),
+        random_dag(&RandomDag::default()),
+    ] {
+        let (num_buffers, schedule) = test.compile();
+        let compact = CompactSchedule::new(num_buffers, &schedule);
+
+        assert_eq!(compact.num_buffers(), num_buffers);
+        assert_eq!(compact.len(), schedule.len());
+        assert_eq!(compact.to_schedule(), schedule);
+
+        for (task, compact_task) in zip(&schedule, compact.iter()) {
+            match (task, compact_task) {
+                (
+                    Task::Node {
+                        id,
+                        inputs,
+                        outputs,
+                    },
+                    CompactTask::Node {
+                        id: compact_id,
+                        inputs: compact_inputs,
+                        outputs: compact_outputs,
+                    },
+                ) => {
+                    assert_eq!(id, compact_id);
+                    assert_eq!(inputs.len(), compact_inputs.len());
+                    assert_eq!(outputs.len(), compact_outputs.len());
+                    assert!(compact_inputs.is_sorted_by_key(|(id, _)| id));
+                    assert!(compact_outputs.is_sorted_by_key(|(id, _)| id));
+                }
+                (
+                    &Task::Sum {
+                        left,
+                        right,
+                        output,
+                    },
+                    compact_task,
+                ) => assert_eq!(
+                    compact_task,
+                    CompactTask::Sum {
+                        left,
+                        right,
+                        output
+                    }
+                ),
+                _ => panic!("task types differ"),
+            }
+        }
+    }
+
+    assert!(CompactSchedule::default().is_empty());
+    assert_eq!(CompactSchedule::default().get(0), None);
+}
+
+#[test]
+fn schedule_cache() {
+    let build = |reversed: bool| {
+        let mut graph = AudioGraph::default();
+
+        let mut source = Node::default();
+        let source_outs = source.add_output_bus(2);
+        let source = graph.insert_node(source);
+
+        let mut master = Node::default();
+        let master_in = master.add_input();
+        let master = graph.insert_node(master);
+
+        let mut edges = Vec::from_iter(
+            source_outs
+                .into_iter()
+                .map(|out| ((source.clone(), out), (master.clone(), master_in.clone()))),
+        );
+
+        if reversed {
+            edges.reverse();
+        }
+
+        for (from, to) in edges {
+            assert!(graph.try_insert_edge(from, to).is_ok_and(id));
+        }
+
+        (graph, source, master)
+    };
+
+    let (mut graph, source, master) = build(false);
+
+    // hashes don't depend on insertion order
+    assert_eq!(graph.content_hash(), build(true).0.content_hash());
+    assert_eq!(graph.content_hash(), graph.snapshot().content_hash());
+
+    let mut cache = ScheduleCache::new(2);
+    let policy = SumBufferPolicy::default();
+    let key = ScheduleKey::new(&graph, [master.clone()], policy);
+
+    assert!(!cache.contains(&key));
+    let compiled = cache.get_or_compile(&graph, [master.clone()], policy);
+    assert_eq!(compiled, graph.compile([master.clone()]));
+    assert!(cache.contains(&key));
+
+    // an identical graph hits the cache
+    let (identical, ..) = build(true);
+    assert_eq!(
+        cache.get_or_compile(&identical, [master.clone(), master.clone()], policy),
+        compiled
+    );
+    assert_eq!(cache.len(), 1);
+
+    graph.set_latency(&source, 16);
+    assert_ne!(graph.content_hash(), key.graph);
+
+    let key2 = ScheduleKey::new(&graph, [master.clone()], policy);
+    cache.get_or_compile(&graph, [master.clone()], policy);
+    assert_eq!(cache.len(), 2);
+
+    // `key` is now the most recently used, `key2` gets evicted
+    assert!(cache.get(&key).is_some());
+    let key3 = ScheduleKey::new(&graph, [master.clone()], SumBufferPolicy::AlwaysNew);
+    cache.get_or_compile(&graph, [master.clone()], SumBufferPolicy::AlwaysNew);
+
+    assert!(cache.contains(&key));
+    assert!(!cache.contains(&key2));
+    assert!(cache.contains(&key3));
+
+    assert!(graph.remove_edge((source, OutputID(0)), (master.clone(), InputID(0))));
+    assert_ne!(graph.content_hash(), key2.graph);
+
+    let mut empty = ScheduleCache::new(0);
+    empty.get_or_compile(&graph, [master], policy);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn layered_layout() {
+    use testing::*;
+
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            filter: [filter_in] => [filter_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> filter.filter_in;
+        filter.filter_out -> master.master_in;
+        source.source_out -> master.master_in;
+    }
+
+    let layout = graph.layout();
+
+    assert_eq!(layout.layers().len(), 3);
+    for (node, layer) in [(&source, 0), (&filter, 1), (&master, 2)] {
+        assert_eq!(layout.position(node).unwrap().layer, layer);
+    }
+
+    // the edge skipping a layer is routed through it
+    let route = layout.route(&source, &master);
+    assert_eq!(route.len(), 1);
+    assert_eq!(route[0].layer, 1);
+    assert!(layout.route(&source, &filter).is_empty());
+    assert_eq!(layout.layers()[1].len(), 2);
+    assert_eq!(layout.crossings(), 0);
+
+    graph! {
+        crossed = {
+            source1: [] => [source1_out];
+            source2: [] => [source2_out];
+            sink1: [sink1_in] => [];
+            sink2: [sink2_in] => [];
+        }
+        source1.source1_out -> sink2.sink2_in;
+        source2.source2_out -> sink1.sink1_in;
+    }
+
+    // nodes are initially ordered by ID, which would make both edges cross
+    let layout = crossed.layout();
+    assert_eq!(layout.crossings(), 0);
+    assert_ne!(
+        layout.position(&source1).unwrap().index < layout.position(&source2).unwrap().index,
+        layout.position(&sink1).unwrap().index < layout.position(&sink2).unwrap().index,
+    );
+
+    for test in [
+        m_shape(4),
+        dense_mixer(3, 2),
+        random_dag(&RandomDag::default()),
+    ] {
+        let layout = test.graph.layout();
+
+        // signal flows from lower to higher layers
+        for (id, node) in test.graph.nodes() {
+            for source in node.inputs().values().flat_map(|i| i.connections().keys()) {
+                let (from, to) = (
+                    layout.position(source).unwrap(),
+                    layout.position(id).unwrap(),
+                );
+                assert!(from.layer < to.layer);
+                assert_eq!(layout.route(source, id).len(), to.layer - from.layer - 1);
+            }
+        }
+
+        assert_eq!(
+            layout
+                .layers()
+                .iter()
+                .flatten()
+                .filter(|slot| matches!(slot, LayoutSlot::Node(_)))
+                .count(),
+            test.graph.nodes().count()
+        );
+    }
+}
+
+#[test]
+fn edge_alignment_delays() {
+    graph! {
+        graph = {
+            source @ 16: [] => [source_out];
+            filter @ 64: [filter_in] => [filter_out];
+            master: [master_in, sidechain] => [];
+        }
+        source.source_out -> filter.filter_in;
+        filter.filter_out -> master.master_in;
+        source.source_out -> master.master_in;
+        source.source_out -> master.sidechain;
+    }
+
+    let delays = graph.alignment_delays([master.clone()]);
+
+    let delay = |from: &NodeID, to: &NodeID, input: &InputID| {
+        let output = graph[from].output_ids().iter().next().unwrap().clone();
+        delays[&((from.clone(), output), (to.clone(), input.clone()))]
+    };
+
+    assert_eq!(delays.len(), 4);
+    assert_eq!(delay(&source, &filter, &filter_in), 0);
+    assert_eq!(delay(&filter, &master, &master_in), 0);
+    // the direct path, summed with the filtered one, is delayed to line up with it
+    assert_eq!(delay(&source, &master, &master_in), 64);
+    assert_eq!(delay(&source, &master, &sidechain), 64);
+
+    assert!(graph
+        .alignment_delays([filter.clone()])
+        .values()
+        .all(|&delay| delay == 0));
+
+    // latencies saturate instead of overflowing
+    graph.set_latency(&filter, u64::MAX);
+
+    let delays = graph.alignment_delays([master.clone()]);
+
+    let delay = |from: &NodeID, to: &NodeID, input: &InputID| {
+        let output = graph[from].output_ids().iter().next().unwrap().clone();
+        delays[&((from.clone(), output), (to.clone(), input.clone()))]
+    };
+
+    assert_eq!(delay(&filter, &master, &master_in), 0);
+    assert_eq!(delay(&source, &master, &master_in), u64::MAX - 16);
+    assert_eq!(delay(&source, &master, &sidechain), u64::MAX - 16);
+}
+
+struct GatingRunner {
+    inner: SummingRunner,
+    gate_open: bool,
+    ran: Vec<NodeID>,
+}
+
+impl TaskRunner for GatingRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        self.ran.push(id.clone());
+        TaskRunner::run_node(&mut self.inner, id, inputs, outputs);
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        TaskRunner::run_sum(&mut self.inner, left, right, output);
+    }
+}
+
+impl GatedTaskRunner for GatingRunner {
+    fn is_open(&mut self, _gate: &NodeID) -> bool {
+        self.gate_open
+    }
+
+    fn silence(&mut self, buffer: usize) {
+        self.inner.buffers[buffer] = 0;
+    }
+}
+
+#[test]
+fn conditional_schedules() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            gate: [gate_in] => [gate_out];
+            fx1: [fx1_in] => [fx1_out];
+            fx2: [fx2_in] => [fx2_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> gate.gate_in;
+        gate.gate_out -> fx1.fx1_in;
+        fx1.fx1_out -> fx2.fx2_in;
+        fx2.fx2_out -> master.master_in;
+        source.source_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+    let mut conditional = ConditionalSchedule::new(schedule);
+
+    assert!(!conditional.gate(&fx1, [gate.clone()]));
+    assert!(!conditional.gate(&gate, [NodeID(u32::MAX)]));
+    assert!(conditional.gates().is_empty());
+
+    assert!(conditional.gate(&gate, [fx1.clone(), fx2.clone()]));
+    assert_eq!(conditional.gates(), core::slice::from_ref(&gate));
+
+    let mut runner = GatingRunner {
+        inner: SummingRunner {
+            buffers: vec![0; num_buffers],
+            received: FnvHashMap::default(),
+        },
+        gate_open: true,
+        ran: vec![],
+    };
+
+    conditional.run(&mut runner);
+    assert_eq!(runner.ran.len(), 5);
+    let open_master_input = runner.inner.received[&master];
+
+    runner.gate_open = false;
+    runner.ran.clear();
+    conditional.run(&mut runner);
+
+    assert_eq!(runner.ran, [source.clone(), gate.clone(), master.clone()]);
+    // only the ungated signal reaches the master node
+    assert_eq!(runner.inner.received[&master], 1 << source.0);
+    assert_ne!(open_master_input, 1 << source.0);
+}
+
+#[test]
+fn tail_lengths() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            delay @ 32: [delay_in] => [delay_out];
+            reverb: [reverb_in] => [reverb_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> delay.delay_in;
+        source.source_out -> reverb.reverb_in;
+        delay.delay_out -> master.master_in;
+        reverb.reverb_out -> master.master_in;
+    }
+
+    assert_eq!(graph.tail_length([master.clone()]), 32);
+
+    graph.get_node_mut(&delay).unwrap().set_tail(1000);
+    graph.get_node_mut(&reverb).unwrap().set_tail(48000);
+    assert_eq!(graph.tail_length([master.clone()]), 48000);
+    assert_eq!(graph.tail_length([delay.clone()]), 1032);
+
+    graph.get_node_mut(&master).unwrap().set_tail(100);
+    assert_eq!(graph.tail_length([master.clone(), delay.clone()]), 48100);
+
+    graph.get_node_mut(&source).unwrap().set_tail(u64::MAX);
+    assert_eq!(graph.tail_length([master]), u64::MAX);
+}
+
+#[test]
+fn node_seeds() {
+    let mut graph = AudioGraph::default();
+    let ids = Vec::from_iter((0..64).map(|_| graph.insert_node(Node::default())));
+
+    let seeds = FnvHashSet::from_iter(ids.iter().map(|id| id.seed(42)));
+    assert_eq!(seeds.len(), ids.len());
+
+    // an identically built graph gets the same seeds
+    let mut rebuilt = AudioGraph::default();
+    for id in &ids {
+        assert_eq!(rebuilt.insert_node(Node::default()).seed(42), id.seed(42));
+        assert_ne!(id.seed(42), id.seed(43));
+    }
+}
+
+#[test]
+fn no_alloc_when_running() {
+    let (_, calls) = count_allocations(|| vec![0u8; 16]);
+    assert_ne!(calls, 0);
+
+    let test = testing::dense_mixer(4, 3);
+    let (num_buffers, schedule) = test.compile();
+
+    let mut runner = SummingRunner {
+        buffers: vec![0; num_buffers],
+        received: FnvHashMap::default(),
+    };
+
+    // let the runner allocate, for the nodes it records, once
+    run_schedule(&schedule, &mut runner);
+    assert_no_alloc(|| run_schedule(&schedule, &mut runner));
+
+    let compact = CompactSchedule::new(num_buffers, &schedule);
+    assert_no_alloc(|| compact.iter().count());
+
+    let received = runner.received.clone();
+    runner.received.clear();
+    assert_no_alloc(|| compact.run(&mut runner));
+    assert_eq!(runner.received, received);
+
+    let mut conditional = ConditionalSchedule::new(schedule);
+    for (source, _) in test
+        .graph
+        .nodes()
+        .filter(|(_, node)| node.inputs().is_empty())
+    {
+        let consumers = Vec::from_iter(test.roots.iter().cloned());
+        assert!(conditional.gate(source, consumers));
+    }
+
+    let mut runner = GatingRunner {
+        inner: runner,
+        gate_open: false,
+        ran: Vec::with_capacity(conditional.schedule().len()),
+    };
+
+    assert_no_alloc(|| conditional.run(&mut runner));
+}
+
+#[test]
+fn batch_edge_insertion() {
+    graph! {
+        graph = {
+            a: [a_in] => [a_out];
+            b: [b_in] => [b_out];
+            c: [c_in] => [c_out];
+        }
+    }
+
+    let edge = |from: &NodeID, output: &OutputID, to: &NodeID, input: &InputID| {
+        ((from.clone(), output.clone()), (to.clone(), input.clone()))
+    };
+
+    let ab = edge(&a, &a_out, &b, &b_in);
+    let bc = edge(&b, &b_out, &c, &c_in);
+    let ca = edge(&c, &c_out, &a, &a_in);
+
+    // cycles made of several of the new edges are detected too
+    assert_eq!(
+        graph.try_insert_edges([ab.clone(), bc.clone(), ca.clone()]),
+        Err(true)
+    );
+    assert_eq!(
+        graph.try_insert_edges([ab.clone(), edge(&b, &b_out, &NodeID(u32::MAX), &c_in)]),
+        Err(false)
+    );
+    assert!(graph.nodes().all(|(_, node)| node
+        .inputs()
+        .values()
+        .all(|input| input.connections().is_empty())));
+
+    // edges going against the maintained order reorder it, which is undone on failure
+    assert_eq!(
+        graph.try_insert_edges([ca.clone(), ab.clone(), bc.clone()]),
+        Err(true)
+    );
+    assert!(graph.nodes().all(|(_, node)| node
+        .inputs()
+        .values()
+        .all(|input| input.connections().is_empty())));
+    fuzz::check_topological_order(&graph);
+
+    assert_eq!(
+        graph.try_insert_edges([ab.clone(), bc.clone(), ab.clone()]),
+        Ok(2)
+    );
+    fuzz::check_topological_order(&graph);
+    assert_eq!(graph.try_insert_edges([bc]), Ok(0));
+    assert_eq!(graph.try_insert_edges([ca]), Err(true));
+    assert_eq!(
+        graph.try_insert_edges([edge(&a, &a_out, &a, &a_in)]),
+        Err(true)
+    );
+
+    let chain = testing::chain(200, 0).graph;
+
+    let edges = Vec::from_iter(chain.nodes().flat_map(|(to, node)| {
+        node.inputs().iter().flat_map(move |(input, connections)| {
+            connections
+                .connections()
+                .iter()
+                .flat_map(move |(from, outputs)| {
+                    outputs.iter().map(move |output| {
+                        ((from.clone(), output.clone()), (to.clone(), input.clone()))
+                    })
+                })
+        })
+    }));
+
+    let mut rebuilt = chain.clone();
+    for (from, to) in edges.iter().cloned() {
+        assert!(rebuilt.remove_edge(from, to));
+    }
+
+    assert_eq!(rebuilt.try_insert_edges(edges), Ok(199));
+    assert_eq!(rebuilt.content_hash(), chain.content_hash());
+    fuzz::check_topological_order(&rebuilt);
+}
+
+#[test]
+fn maintained_topological_order() {
+    use fuzz::check_topological_order;
+
+    graph! {
+        graph = {
+            a: [a_in] => [a_out];
+            b: [b_in] => [b_out];
+            c: [c_in] => [c_out];
+            d: [d_in] => [d_out];
+        }
+        // against the insertion order, so that nodes must be reordered
+        d.d_out -> c.c_in;
+        c.c_out -> b.b_in;
+        b.b_out -> a.a_in;
+    }
+
+    check_topological_order(&graph);
+    assert_eq!(
+        Vec::from_iter(graph.topological_order().cloned()),
+        [d.clone(), c.clone(), b.clone(), a.clone()]
+    );
+
+    assert_eq!(
+        graph.try_insert_edge((a.clone(), a_out.clone()), (d.clone(), d_in.clone())),
+        Err(true)
+    );
+
+    let snapshot = graph.snapshot();
+    graph.remove_node(&c);
+    let e = graph.insert_node(Node::default());
+    check_topological_order(&graph);
+    check_topological_order(&snapshot);
+    assert!(graph.topological_order().any(|id| id == &e));
+
+    assert_eq!(
+        graph.try_insert_edge((a.clone(), a_out), (d.clone(), d_in)),
+        Ok(true)
+    );
+    check_topological_order(&graph);
+
+    let test = testing::random_dag(&testing::RandomDag {
+        nodes: 300,
+        max_fan_in: 3,
+        max_fan_out: 3,
+        latency: 0..1,
+        seed: 7,
+    });
+    check_topological_order(&test.graph);
+}
+
+impl TracingRunner for SummingRunner {
+    fn buffer_stats(&self, buffer: usize) -> BufferStats {
+        let value = self.buffers[buffer];
+
+        BufferStats {
+            peak: value as f32,
+            checksum: value,
+        }
+    }
+}
+
+#[test]
+fn block_traces() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            filter: [filter_in] => [filter_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> filter.filter_in;
+        filter.filter_out -> master.master_in;
+        source.source_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+
+    let mut runner = SummingRunner {
+        buffers: vec![0; num_buffers],
+        received: FnvHashMap::default(),
+    };
+
+    let tracer = BlockTracer::new();
+    assert_eq!(tracer.run_block(&schedule, &mut runner), None);
+
+    tracer.request();
+    assert!(tracer.is_requested());
+    runner.buffers.fill(0);
+    let trace = tracer.run_block(&schedule, &mut runner).unwrap();
+    assert!(!tracer.is_requested());
+    assert_eq!(tracer.run_block(&schedule, &mut runner), None);
+
+    assert_eq!(
+        Vec::from_iter(trace.tasks.iter().map(|task| task.task.clone())),
+        schedule
+    );
+
+    for TracedTask { task, buffers, .. } in &trace.tasks {
+        if let Task::Node { id, outputs, .. } = task {
+            for &output in outputs.values() {
+                let traced = buffers.iter().find(|b| b.buffer == output).unwrap();
+                assert_eq!(traced.after.checksum, runner.received[id] + (1 << id.0));
+            }
+        }
+    }
+
+    let json = trace.to_json();
+    assert!(json.starts_with("{\"elapsed_ns\":"));
+    assert!(json.contains(&format!("\"node\":{}", filter.0)));
+    assert!(json.contains("\"sum\":{"));
+    assert_eq!(
+        json.matches('{').count(),
+        json.matches('}').count(),
+        "{json}"
+    );
+}
+
+struct TappingRunner {
+    inner: SummingRunner,
+    rings: Vec<TapRing>,
+}
+
+impl TaskRunner for TappingRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        TaskRunner::run_node(&mut self.inner, id, inputs, outputs);
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        TaskRunner::run_sum(&mut self.inner, left, right, output);
+    }
+}
+
+impl TapRunner for TappingRunner {
+    fn tap(&mut self, tap: usize, buffer: usize) {
+        self.rings[tap].push(&[self.inner.buffers[buffer] as f32]);
+    }
+}
+
+#[test]
+fn tapped_schedules() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            filter: [filter_in] => [filter_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> filter.filter_in;
+        filter.filter_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+    let mut tapped = TappedSchedule::new(
+        schedule,
+        [
+            (filter.clone(), filter_out.clone()),
+            (source.clone(), source_out.clone()),
+        ],
+    );
+    assert!(tapped.is_active(0) && tapped.is_active(1));
+
+    let mut runner = TappingRunner {
+        inner: SummingRunner {
+            buffers: vec![0; num_buffers],
+            // so that recording what nodes received doesn't allocate
+            received: FnvHashMap::with_capacity_and_hasher(4, Default::default()),
+        },
+        rings: Vec::from_iter(iter::repeat_with(|| TapRing::new(4, 1)).take(2)),
+    };
+
+    assert_no_alloc(|| tapped.run(&mut runner));
+
+    let mut frame = [0.; 4];
+    assert_eq!(runner.rings[0].read_latest(&mut frame), 1);
+    assert_eq!(frame[0], ((1 << source.0) + (1 << filter.0)) as f32);
+    assert_eq!(runner.rings[1].read_latest(&mut frame), 1);
+    assert_eq!(frame[0], (1 << source.0) as f32);
+
+    // taps keep their indices across schedule swaps, and go inactive
+    // if their ports aren't scheduled anymore
+    let (_, schedule) = graph.compile([filter.clone()]);
+    tapped.swap_schedule(schedule);
+    assert_eq!(tapped.taps().len(), 2);
+    assert!(!tapped.is_active(0) && tapped.is_active(1));
+
+    tapped.run(&mut runner);
+    assert_eq!(runner.rings[0].read_latest(&mut frame), 1);
+    assert_eq!(runner.rings[1].read_latest(&mut frame), 2);
+}
+
+struct HookedRunner {
+    inner: GatingRunner,
+    taps: Vec<(usize, u64)>,
+}
+
+impl TaskRunner for HookedRunner {
+    fn run_node(
+        &mut self,
+        id: &NodeID,
+        inputs: &FnvHashMap<InputID, usize>,
+        outputs: &FnvHashMap<OutputID, usize>,
+    ) {
+        self.inner.run_node(id, inputs, outputs);
+    }
+
+    fn run_sum(&mut self, left: usize, right: usize, output: usize) {
+        self.inner.run_sum(left, right, output);
+    }
+}
+
+impl GatedTaskRunner for HookedRunner {
+    fn is_open(&mut self, gate: &NodeID) -> bool {
+        self.inner.is_open(gate)
+    }
+
+    fn silence(&mut self, buffer: usize) {
+        self.inner.silence(buffer);
+    }
+}
+
+impl TapRunner for HookedRunner {
+    fn tap(&mut self, tap: usize, buffer: usize) {
+        self.taps.push((tap, self.inner.inner.buffers[buffer]));
+    }
+}
+
+impl TracingRunner for HookedRunner {
+    fn buffer_stats(&self, buffer: usize) -> BufferStats {
+        self.inner.inner.buffer_stats(buffer)
+    }
+}
+
+#[test]
+fn composed_task_hooks() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            gate: [gate_in] => [gate_out];
+            fx: [fx_in] => [fx_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> gate.gate_in;
+        gate.gate_out -> fx.fx_in;
+        fx.fx_out -> master.master_in;
+        source.source_out -> master.master_in;
+    }
+
+    let (num_buffers, schedule) = graph.compile([master.clone()]);
+
+    let mut conditional = ConditionalSchedule::new(schedule.clone());
+    assert!(conditional.gate(&gate, [fx.clone()]));
+    let tapped = TappedSchedule::new(schedule.clone(), [(fx.clone(), fx_out)]);
+    let mut monitor = DeadlineMonitor::new(1, 48000.);
+    let tracer = BlockTracer::new();
+    tracer.request();
+
+    let mut runner = HookedRunner {
+        inner: GatingRunner {
+            inner: SummingRunner {
+                buffers: vec![1; num_buffers],
+                received: FnvHashMap::default(),
+            },
+            gate_open: false,
+            ran: vec![],
+        },
+        taps: vec![],
+    };
+
+    let (gated, gates) = conditional.hook();
+    let (_, taps) = tapped.hook();
+    let mut deadline = monitor.hook(48000);
+    let mut trace = tracer.hook();
+
+    run_schedule_with(
+        gated,
+        &mut runner,
+        &mut (gates, (taps, (&mut deadline, &mut trace))),
+    );
+
+    assert!(deadline.finish());
+    assert_eq!(monitor.blocks_run(), 1);
+
+    // the gated task is skipped, silencing it's output, which is still tapped, and traced
+    assert_eq!(runner.inner.ran, [source, gate, master.clone()]);
+    assert_eq!(runner.taps, [(0, 0)]);
+
+    let trace = trace.unwrap().finish();
+    assert_eq!(
+        Vec::from_iter(trace.tasks.iter().map(|task| task.task.clone())),
+        schedule
+    );
+
+    for traced in &trace.tasks {
+        let is_fx = matches!(&traced.task, Task::Node { id, .. } if id == &fx);
+        assert_eq!(traced.ran, !is_fx);
+    }
+}
+
+#[test]
+fn tap_rings() {
+    let ring = TapRing::new(4, 3);
+    let mut frame = [0.; 8];
+    assert_eq!(ring.read_latest(&mut frame), 0);
+
+    let samples = Vec::from_iter((0..10).map(|i| i as f32));
+    ring.push(&samples[..4]);
+    assert_eq!(ring.read_latest(&mut frame), 2);
+    assert_eq!(frame[..2], [0., 3.]);
+
+    // decimation carries over between pushes
+    ring.push(&samples[4..]);
+    assert_eq!(ring.read_latest(&mut frame), 4);
+    assert_eq!(frame[..4], [0., 3., 6., 9.]);
+
+    ring.push(&[10., 11., 12., 13.]);
+    assert_eq!(ring.read_latest(&mut frame[..3]), 3);
+    assert_eq!(frame[..3], [6., 9., 12.]);
+
+    let ring = std::sync::Arc::new(TapRing::new(64, 1));
+    let reader = {
+        let ring = ring.clone();
+        std::thread::spawn(move || {
+            let mut frame = [0.; 64];
+            for _ in 0..1000 {
+                let len = ring.read_latest(&mut frame);
+                assert!(frame[..len].iter().all(|&s| s >= 0.));
+            }
+        })
+    };
+
+    for i in 0..1000 {
+        ring.push(&[i as f32; 16]);
+    }
+    reader.join().unwrap();
+}
+
+#[test]
+fn graph_editor_root_removal() {
+    graph! {
+        graph = {
+            source: [] => [source_out];
+            master: [master_in] => [];
+        }
+        source.source_out -> master.master_in;
+    }
+
+    let (mut editor, outcomes) = GraphEditor::new(graph, [source.clone(), master.clone()]);
+    let sender = editor.command_sender();
+
+    sender.send(GraphEdit::RemoveNode(master.clone())).unwrap();
+    let request = editor.apply_pending().unwrap().unwrap();
+    assert!(matches!(
+        outcomes.recv(),
+        Ok(EditOutcome::NodeRemoved(Some(_)))
+    ));
+    assert_eq!(editor.root_nodes(), core::slice::from_ref(&source));
+
+    let result = editor.compiler().recv().unwrap();
+    assert_eq!(result.request, request);
+    assert_eq!(result.schedule, editor.graph().compile([source.clone()]).1);
+
+    // the new node reuses the removed root's id, but isn't a root
+    sender.send(GraphEdit::InsertNode(Node::default())).unwrap();
+    let request = editor.apply_pending().unwrap().unwrap();
+    assert!(matches!(outcomes.recv(), Ok(EditOutcome::NodeInserted(id)) if id == master));
+    assert_eq!(editor.root_nodes(), core::slice::from_ref(&source));
+
+    let result = editor.compiler().recv().unwrap();
+    assert_eq!(result.request, request);
+    assert_eq!(result.schedule, editor.graph().compile([source]).1);
+}
+
+#[test]
+fn compile_service_failures() {
+    graph! {
+        graph = {
+            master: [_master_in] => [];
+        }
+    }
+
+    let mut service = CompileService::new();
+
+    let missing = NodeID(u32::MAX);
+    let request = service
+        .request(graph.snapshot(), [master.clone(), missing.clone()])
+        .unwrap();
+    assert_eq!(
+        service.recv(),
+        Err(CompileError::MissingRootNode {
+            request,
+            node: missing
+        })
+    );
+
+    // the service keeps running
+    let request = service.request(graph.snapshot(), [master.clone()]).unwrap();
+    assert_eq!(
+        service.recv(),
+        Ok(CompiledSchedule {
+            request,
+            num_buffers: 0,
+            schedule: graph.compile([master]).1,
+        })
+    );
+}
@@ -0,0 +1,75 @@
+use super::*;
+use core::ops::RangeInclusive;
+
+/// A topological order of a graph's nodes, sources first, kept valid as nodes and edges
+/// are inserted, after Pearce and Kelly's dynamic topological sort. An inserted edge can
+/// only create a cycle if it goes against the current order, and then only the nodes
+/// ranked between it's ends need to be searched, and reordered.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TopologicalOrder {
+    // nodes by rank, `None` where nodes were removed
+    slots: Vec<Option<NodeID>>,
+    ranks: FnvHashMap<NodeID, usize>,
+}
+
+impl TopologicalOrder {
+    pub(crate) fn rank(&self, node: &NodeID) -> usize {
+        self.ranks[node]
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &NodeID> {
+        self.slots.iter().flatten()
+    }
+
+    /// Ranks `node` after all other nodes, which is always valid for new nodes,
+    /// as no other node can depend on them yet.
+    pub(crate) fn push(&mut self, node: NodeID) {
+        self.ranks.insert(node.clone(), self.slots.len());
+        self.slots.push(Some(node));
+    }
+
+    pub(crate) fn remove(&mut self, node: &NodeID) {
+        let rank = self
+            .ranks
+            .remove(node)
+            .expect("INTERNAL ERROR: removed node must be ranked");
+        self.slots[rank] = None;
+
+        if self.ranks.len() < self.slots.len() / 2 {
+            self.slots.retain(Option::is_some);
+            self.rerank();
+        }
+    }
+
+    /// Ranks the nodes in `earlier`, all ranked within `ranks`, before the other nodes
+    /// ranked within `ranks`, keeping their relative orders otherwise.
+    pub(crate) fn move_before(
+        &mut self,
+        ranks: RangeInclusive<usize>,
+        earlier: &FnvHashSet<NodeID>,
+    ) {
+        // removed nodes' slots stay where they are
+        let occupied = Vec::from_iter(ranks.filter(|&rank| self.slots[rank].is_some()));
+
+        let (mut reordered, later): (Vec<_>, Vec<_>) = occupied
+            .iter()
+            .map(|&rank| self.slots[rank].take().unwrap())
+            .partition(|node| earlier.contains(node));
+        reordered.extend(later);
+
+        for (rank, node) in iter::zip(occupied, reordered) {
+            self.ranks.insert(node.clone(), rank);
+            self.slots[rank] = Some(node);
+        }
+    }
+
+    fn rerank(&mut self) {
+        self.ranks.clear();
+
+        for (rank, node) in self.slots.iter().enumerate() {
+            if let Some(node) = node {
+                self.ranks.insert(node.clone(), rank);
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use super::*;
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::time::{Duration, Instant};
+
+/// A summary of a buffer's contents, see [`TracingRunner`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BufferStats {
+    /// Largest absolute sample value, `NaN` if any sample is `NaN`
+    pub peak: f32,
+    /// Any hash of the buffer's samples, to tell whether it's contents changed
+    pub checksum: u64,
+}
+
+/// A [`TaskRunner`] that can summarize the contents of it's buffers, for [`BlockTracer`]s.
+pub trait TracingRunner: TaskRunner {
+    fn buffer_stats(&self, buffer: usize) -> BufferStats;
+}
+
+/// A buffer used by a [`TracedTask`], and it's contents before and after the task ran.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TracedBuffer {
+    pub buffer: usize,
+    pub before: BufferStats,
+    pub after: BufferStats,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TracedTask {
+    pub task: Task,
+    /// Whether the task ran, or was skipped by a [`TaskHook`]
+    pub ran: bool,
+    /// Every buffer the task reads or writes, sorted
+    pub buffers: Vec<TracedBuffer>,
+    pub elapsed: Duration,
+}
+
+/// Everything that happened while running a schedule for one block, see [`BlockTracer`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockTrace {
+    /// In schedule order
+    pub tasks: Vec<TracedTask>,
+    pub elapsed: Duration,
+}
+
+impl BlockTrace {
+    /// Returns this trace as a JSON object, for users to attach to bug reports.
+    ///
+    /// Non-finite peaks are written as the strings `"NaN"`, `"inf"` and `"-inf"`.
+    #[inline]
+    pub fn to_json(&self) -> String {
+        fn ports<P: Ord>(ports: &FnvHashMap<P, usize>, id: impl Fn(&P) -> u32) -> String {
+            let mut ports = Vec::from_iter(ports.iter());
+            ports.sort_unstable_by_key(|&(port, _)| port);
+
+            let ports = Vec::from_iter(
+                ports
+                    .into_iter()
+                    .map(|(port, buffer)| format!("[{},{buffer}]", id(port))),
+            );
+            format!("[{}]", ports.join(","))
+        }
+
+        fn stats(BufferStats { peak, checksum }: BufferStats) -> String {
+            let peak = if peak.is_finite() {
+                peak.to_string()
+            } else {
+                format!("\"{peak}\"")
+            };
+
+            format!("{{\"peak\":{peak},\"checksum\":{checksum}}}")
+        }
+
+        let mut out = format!("{{\"elapsed_ns\":{},\"tasks\":[", self.elapsed.as_nanos());
+
+        for (i, traced) in self.tasks.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+
+            write!(out, "{{\"index\":{i},").unwrap();
+
+            match &traced.task {
+                Task::Node {
+                    id,
+                    inputs,
+                    outputs,
+                } => write!(
+                    out,
+                    "\"node\":{},\"inputs\":{},\"outputs\":{},",
+                    id.0,
+                    ports(inputs, |input| input.0),
+                    ports(outputs, |output| output.0),
+                ),
+                Task::Sum {
+                    left,
+                    right,
+                    output,
+                } => write!(
+                    out,
+                    "\"sum\":{{\"left\":{left},\"right\":{right},\"output\":{output}}},"
+                ),
+            }
+            .unwrap();
+
+            let buffers = Vec::from_iter(traced.buffers.iter().map(
+                |&TracedBuffer {
+                     buffer,
+                     before,
+                     after,
+                 }| {
+                    format!(
+                        "{{\"buffer\":{buffer},\"before\":{},\"after\":{}}}",
+                        stats(before),
+                        stats(after)
+                    )
+                },
+            ));
+
+            write!(
+                out,
+                "\"ran\":{},\"buffers\":[{}],\"elapsed_ns\":{}}}",
+                traced.ran,
+                buffers.join(","),
+                traced.elapsed.as_nanos()
+            )
+            .unwrap();
+        }
+
+        out.push_str("]}");
+        out
+    }
+}
+
+/// Runs schedules, tracing a single block when requested, e. g. when the user reports
+/// a problem, to diagnose misrouted signals or `NaN` sources from the field.
+///
+/// Requests can be made from any thread, by sharing the tracer. Traced blocks allocate,
+/// and take longer to process, other blocks don't.
+#[derive(Debug, Default)]
+pub struct BlockTracer {
+    requested: AtomicBool,
+}
+
+impl BlockTracer {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the next call to [`Self::run_block`] (or [`Self::hook`]) trace it's block.
+    #[inline]
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Returns a hook tracing the current block, if a trace was requested, to run it along
+    /// with other hooks, through [`run_schedule_with`].
+    #[inline]
+    pub fn hook(&self) -> Option<TraceHook> {
+        self.requested
+            .swap(false, Ordering::Relaxed)
+            .then(TraceHook::new)
+    }
+
+    /// Runs `schedule` for one block, returns it's trace if one was requested.
+    #[inline]
+    pub fn run_block(
+        &self,
+        schedule: &[Task],
+        runner: &mut (impl TracingRunner + ?Sized),
+    ) -> Option<BlockTrace> {
+        let mut hook = self.hook();
+        run_schedule_with(schedule, runner, &mut hook);
+        hook.map(TraceHook::finish)
+    }
+}
+
+/// Runs `schedule` for one block, recording it's trace.
+#[inline]
+pub fn trace_block(schedule: &[Task], runner: &mut (impl TracingRunner + ?Sized)) -> BlockTrace {
+    let mut hook = TraceHook::new();
+    run_schedule_with(schedule, runner, &mut hook);
+    hook.finish()
+}
+
+/// A [`TaskHook`] recording the trace of one block, see [`BlockTracer::hook`]. Allocates.
+#[derive(Debug)]
+pub struct TraceHook {
+    start: Instant,
+    tasks: Vec<TracedTask>,
+    // of the current task
+    buffers: Vec<(usize, BufferStats)>,
+    task_start: Instant,
+}
+
+impl Default for TraceHook {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceHook {
+    /// Starts timing the block.
+    #[inline]
+    pub fn new() -> Self {
+        let start = Instant::now();
+
+        Self {
+            start,
+            tasks: vec![],
+            buffers: vec![],
+            task_start: start,
+        }
+    }
+
+    /// Stops timing the block, returns it's trace.
+    #[inline]
+    pub fn finish(self) -> BlockTrace {
+        BlockTrace {
+            tasks: self.tasks,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
+
+impl<R: TracingRunner + ?Sized> TaskHook<R> for TraceHook {
+    #[inline]
+    fn before(&mut self, _index: usize, task: &Task, runner: &mut R) -> bool {
+        let mut buffers = match task {
+            Task::Node {
+                inputs, outputs, ..
+            } => Vec::from_iter(inputs.values().chain(outputs.values()).copied()),
+            &Task::Sum {
+                left,
+                right,
+                output,
+            } => vec![left, right, output],
+        };
+        buffers.sort_unstable();
+        buffers.dedup();
+
+        self.buffers = Vec::from_iter(
+            buffers
+                .into_iter()
+                .map(|buffer| (buffer, runner.buffer_stats(buffer))),
+        );
+
+        self.task_start = Instant::now();
+        true
+    }
+
+    #[inline]
+    fn after(&mut self, _index: usize, task: &Task, ran: bool, runner: &mut R) {
+        let elapsed = self.task_start.elapsed();
+
+        self.tasks.push(TracedTask {
+            task: task.clone(),
+            ran,
+            buffers: self
+                .buffers
+                .drain(..)
+                .map(|(buffer, before)| TracedBuffer {
+                    buffer,
+                    before,
+                    after: runner.buffer_stats(buffer),
+                })
+                .collect(),
+            elapsed,
+        });
+    }
+}